@@ -0,0 +1,161 @@
+//! Broad phase under bullet hell load: `cargo bench --bench collision`
+//! Thousands of projectiles moving over the window every frame, the spatial hash
+//! has to find the interacting pairs well within a 60 FPS frame.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::Vec2;
+use bevy_oyun::collision::spatial::SpatialHash;
+use bevy_oyun::collision::CollisionLayer;
+use bevy_oyun::{COLLISION_CELL_SIZE, SPRITE_SCALE, TIME_STEP, WINDOW_SIZE};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PROJECTILES: usize = 5000;
+const ENEMIES: usize = 40;
+const FRAMES: usize = 600;
+const NAIVE_FRAMES: usize = 20;
+const FRAME_BUDGET: f64 = 1000. / 60.;
+
+struct Body {
+    position: Vec2,
+    velocity: Vec2,
+    size: Vec2,
+    layer: CollisionLayer,
+}
+
+fn world() -> Vec<Body> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let (w, h) = (WINDOW_SIZE.0 / 2., WINDOW_SIZE.1 / 2.);
+    let random_position = |rng: &mut StdRng| Vec2::new(rng.gen_range(-w..w), rng.gen_range(-h..h));
+
+    let mut bodies = vec![Body {
+        position: Vec2::new(0., -h + 50.),
+        velocity: Vec2::ZERO,
+        size: Vec2::new(112., 75.) * SPRITE_SCALE,
+        layer: CollisionLayer::Player,
+    }];
+    for _ in 0..ENEMIES {
+        bodies.push(Body {
+            position: random_position(&mut rng),
+            velocity: Vec2::new(rng.gen_range(-100.0..100.), rng.gen_range(-100.0..100.)),
+            size: Vec2::new(103., 84.) * SPRITE_SCALE,
+            layer: CollisionLayer::Enemy,
+        });
+    }
+    for i in 0..PROJECTILES {
+        let (layer, speed) = if i % 4 == 0 {
+            (CollisionLayer::PlayerProjectile, 500.)
+        } else {
+            (CollisionLayer::EnemyProjectile, -300.)
+        };
+        bodies.push(Body {
+            position: random_position(&mut rng),
+            velocity: Vec2::new(rng.gen_range(-50.0..50.), speed),
+            size: Vec2::new(9., 54.) * SPRITE_SCALE,
+            layer,
+        });
+    }
+    bodies
+}
+
+/// Moves everything and wraps it around the window, so the density stays the same.
+fn step(bodies: &mut [Body]) {
+    let half = Vec2::new(WINDOW_SIZE.0, WINDOW_SIZE.1) / 2.;
+    for body in bodies.iter_mut() {
+        body.position += body.velocity * TIME_STEP;
+        let wrapped = body.position + half;
+        body.position = Vec2::new(
+            wrapped.x.rem_euclid(half.x * 2.),
+            wrapped.y.rem_euclid(half.y * 2.),
+        ) - half;
+    }
+}
+
+fn overlap(a: &Body, b: &Body) -> bool {
+    let d = (a.position - b.position).abs();
+    let r = (a.size + b.size) / 2.;
+    d.x <= r.x && d.y <= r.y
+}
+
+/// Prints the frame times and returns the worst one, in milliseconds.
+fn report(name: &str, times: &[Duration], hits: usize) -> f64 {
+    let ms: Vec<f64> = times.iter().map(|t| t.as_secs_f64() * 1000.).collect();
+    let avg = ms.iter().sum::<f64>() / ms.len() as f64;
+    let worst = ms.iter().cloned().fold(0., f64::max);
+    println!(
+        "{:<8} avg {:>8.3} ms  worst {:>8.3} ms  ({:.0}% of a 60 FPS frame)  {} hits",
+        name,
+        avg,
+        worst,
+        worst / FRAME_BUDGET * 100.,
+        hits
+    );
+    worst
+}
+
+fn main() {
+    let mut bodies = world();
+    println!(
+        "{} bodies ({} projectiles), {} frames",
+        bodies.len(),
+        PROJECTILES,
+        FRAMES
+    );
+
+    // broad and narrow phase, like the game does it
+    let mut hash = SpatialHash::new(COLLISION_CELL_SIZE);
+    let mut times = Vec::with_capacity(FRAMES);
+    let mut frame_hits = Vec::with_capacity(FRAMES);
+    for _ in 0..FRAMES {
+        step(&mut bodies);
+        let start = Instant::now();
+        let mut hits = 0;
+        hash.clear();
+        for body in bodies.iter() {
+            hash.insert(body.position, body.size);
+        }
+        hash.pairs(|a, b| {
+            if bodies[a].layer.interacts(bodies[b].layer) && overlap(&bodies[a], &bodies[b]) {
+                hits += 1;
+            }
+        });
+        times.push(start.elapsed());
+        frame_hits.push(hits);
+    }
+    let worst = report("spatial", &times, frame_hits.iter().sum());
+
+    // the old all-pairs check, only the first few frames of it again
+    let mut bodies = world();
+    let mut times = Vec::with_capacity(NAIVE_FRAMES);
+    let mut hits = 0;
+    for _ in 0..NAIVE_FRAMES {
+        step(&mut bodies);
+        let start = Instant::now();
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                if a.layer.interacts(b.layer) && overlap(a, b) {
+                    hits += 1;
+                }
+            }
+        }
+        times.push(start.elapsed());
+    }
+    report("naive", &times, hits);
+
+    let spatial_hits: usize = frame_hits[..NAIVE_FRAMES].iter().sum();
+    if spatial_hits != hits {
+        eprintln!(
+            "the spatial hash found {} hits in the first {} frames, all pairs {}",
+            spatial_hits, NAIVE_FRAMES, hits
+        );
+        std::process::exit(1);
+    }
+    if worst > FRAME_BUDGET {
+        eprintln!(
+            "worst frame {:.3} ms is over the {:.3} ms of a 60 FPS frame",
+            worst, FRAME_BUDGET
+        );
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,851 @@
+//! Draws, edits and previews the formations enemies fly in.
+//!
+//!     cargo run --bin formation_editor -- <name>
+//!
+//! `Elips` and `PointTop` open the circling formations in `assets/orbits.ron`, any other
+//! name a path in `assets/formations/<name>.ron`, which is started anew if there is none
+//! or the game would not load it, the status line says why.
+//! The dim frame is the game's default window, enemies enter and leave outside it.
+//!
+//!  left drag          move a handle
+//!  left, right        preview speed, not saved
+//!  space              send an enemy now
+//!  p                  keep sending enemies on and off
+//!  c                  clear the enemies
+//!  ctrl + s           save
+//!
+//! Paths:
+//!
+//!  right click        add a point at the end (a whole curve for Bezier)
+//!  delete, backspace  remove the point under the mouse
+//!  tab                switch between Waypoints and CatmullRom
+//!  up, down           path speed
+//!  e                  cycle the end: Stop, Exit, Repeat
+//!
+//! Circling formations, the handles set the `(from, to)` ranges new enemies pick from:
+//! where they come in (green, mirrored on the other side), the pivot (orange) and the
+//! smallest and largest ellipse around it (blue).
+//!
+//!  up, down           members sharing a start and pivot
+//!  a                  start circling towards the start or at angle 0
+
+use std::f32::consts::TAU;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_oyun::data::{asset_path, load_ron, save_ron};
+use bevy_oyun::enemy::formation::{EnemyMovement, Formation, FormationKind, FormationMaker};
+use bevy_oyun::enemy::orbit::{OrbitFormation, OrbitFormations};
+use bevy_oyun::enemy::path::{PathEnd, PathFollower, PathFormation, PathFormations, PathShape};
+use bevy_oyun::rng::GameRng;
+use bevy_oyun::{WinSize, FONT, FORMATIONS_DIR, ORBITS_FILE, SPRITE_SCALE, TIME_STEP, WINDOW_SIZE};
+
+/// Room around the game's window, in screen pixels.
+const EDITOR_MARGIN: f32 = 100.;
+/// World pixels per screen pixel, enemies circling in come from far off screen.
+const VIEW_SCALE: f32 = 1.6;
+const PREVIEW_SPRITE: &str = "enemyGreen3.png";
+const PREVIEWS_MAX: usize = 12;
+/// seconds between enemies while sending them
+const PREVIEW_INTERVAL: f32 = 1.;
+const PREVIEW_SPEED_MIN: f32 = 0.125;
+const PREVIEW_SPEED_MAX: f32 = 8.;
+const SPEED_STEP: f32 = 10.;
+/// points drawn per ellipse
+const ELLIPSE_SEGMENTS: usize = 48;
+/// sizes on screen, in screen pixels
+const POINT_SIZE: f32 = 9.;
+/// how close the mouse has to be to grab a point
+const PICK_RADIUS: f32 = 12.;
+const LINE_WIDTH: f32 = 2.;
+const FONT_SIZE: f32 = 16.;
+
+const PATH_COLOR: Color = Color::WHITE;
+const HANDLE_LINE_COLOR: Color = Color::rgb(0.4, 0.4, 0.5);
+const START_COLOR: Color = Color::rgb(0.3, 1., 0.4);
+const POINT_COLOR: Color = Color::rgb(1., 0.85, 0.2);
+const CONTROL_COLOR: Color = Color::rgb(0.5, 0.6, 1.);
+const PIVOT_COLOR: Color = Color::rgb(1., 0.6, 0.1);
+
+fn main() {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| panic!("usage: formation_editor <name>"));
+    let mut message = String::new();
+    let (file, edited) = match name.as_str() {
+        "Elips" | "PointTop" => {
+            let kind = if name == "Elips" {
+                FormationKind::Elips
+            } else {
+                FormationKind::PointTop
+            };
+            let orbits = OrbitFormations::load(ORBITS_FILE)
+                .unwrap_or_else(|err| panic!("could not load orbit formations: {}", err));
+            (asset_path(ORBITS_FILE), Edited::Orbit(kind, orbits))
+        }
+        _ => {
+            let file = asset_path(FORMATIONS_DIR).join(format!("{}.ron", name));
+            let loaded = if file.exists() {
+                load_path(&file)
+            } else {
+                Ok(new_formation())
+            };
+            let formation = loaded.unwrap_or_else(|err| {
+                message = format!("not loaded, started anew: {}", err);
+                new_formation()
+            });
+            (file, Edited::Path(formation))
+        }
+    };
+    let header = comment_header(&file);
+    let saved = message.is_empty();
+
+    App::new()
+        .insert_resource(WindowDescriptor {
+            title: format!("formation editor: {}", name),
+            width: WINDOW_SIZE.0 + 2. * EDITOR_MARGIN,
+            height: WINDOW_SIZE.1 + 2. * EDITOR_MARGIN,
+            resizable: false,
+            ..Default::default()
+        })
+        .insert_resource(ClearColor(Color::rgb(0.08, 0.08, 0.12)))
+        .insert_resource(Editor {
+            file,
+            header,
+            edited,
+            dragging: None,
+            preview_speed: 1.,
+            sending: true,
+            send_timer: Timer::from_seconds(PREVIEW_INTERVAL, true),
+            ticks: 0.,
+            maker: FormationMaker::default(),
+            rng: GameRng::from_entropy(),
+            redraw: true,
+            saved,
+            message,
+        })
+        .add_plugins(DefaultPlugins)
+        .add_startup_system(setup_system)
+        .add_system(mouse_system)
+        .add_system(keyboard_system)
+        .add_system(redraw_system.after(mouse_system).after(keyboard_system))
+        .add_system(preview_system.after(redraw_system))
+        .add_system(status_system.after(redraw_system))
+        .run();
+}
+
+fn new_formation() -> PathFormation {
+    PathFormation {
+        name: String::new(),
+        shape: PathShape::CatmullRom(vec![(-360., 250.), (0., 0.), (360., 250.)]),
+        speed: 250.,
+        speed_curve: Vec::new(),
+        end: PathEnd::Exit,
+    }
+}
+
+/// Checked like `PathFormations::load` does, the preview cannot draw a broken path.
+fn load_path(file: &Path) -> Result<PathFormation, String> {
+    let formation: PathFormation = load_ron(file).map_err(|err| err.to_string())?;
+    formation.validate()?;
+    Ok(formation)
+}
+
+/// The comment lines a file starts with, kept when it is saved over.
+fn comment_header(file: &Path) -> String {
+    fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .take_while(|line| line.trim_start().starts_with("//"))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// The game's default window, the editor shows everything at that size.
+fn default_win_size() -> WinSize {
+    WinSize {
+        w: WINDOW_SIZE.0,
+        h: WINDOW_SIZE.1,
+    }
+}
+
+enum Edited {
+    Path(PathFormation),
+    /// `Elips` or `PointTop`, the other one is saved as it was
+    Orbit(FormationKind, OrbitFormations),
+}
+
+struct Editor {
+    file: PathBuf,
+    header: String,
+    edited: Edited,
+    /// index into `handles`
+    dragging: Option<usize>,
+    /// times the real speed
+    preview_speed: f32,
+    sending: bool,
+    send_timer: Timer,
+    /// game ticks not run yet for circling enemies, they move a tick at a time like in the game
+    ticks: f32,
+    maker: FormationMaker,
+    rng: GameRng,
+    /// the formation changed and has to be drawn again
+    redraw: bool,
+    saved: bool,
+    /// the outcome of the last save
+    message: String,
+}
+
+impl Editor {
+    fn changed(&mut self) {
+        self.redraw = true;
+        self.saved = false;
+        self.message.clear();
+    }
+
+    fn orbit(&self) -> Option<&OrbitFormation> {
+        match &self.edited {
+            Edited::Path(_) => None,
+            Edited::Orbit(FormationKind::PointTop, orbits) => Some(&orbits.point_top),
+            Edited::Orbit(_, orbits) => Some(&orbits.elips),
+        }
+    }
+
+    fn orbit_mut(&mut self) -> Option<&mut OrbitFormation> {
+        match &mut self.edited {
+            Edited::Path(_) => None,
+            Edited::Orbit(FormationKind::PointTop, orbits) => Some(&mut orbits.point_top),
+            Edited::Orbit(_, orbits) => Some(&mut orbits.elips),
+        }
+    }
+
+    /// The points that can be dragged around.
+    fn handles(&self) -> Vec<Vec2> {
+        match &self.edited {
+            Edited::Path(formation) => formation.shape.points(),
+            Edited::Orbit(..) => orbit_handles(self.orbit().unwrap()).to_vec(),
+        }
+    }
+
+    fn move_handle(&mut self, index: usize, to: Vec2) {
+        match &mut self.edited {
+            Edited::Path(formation) => formation.shape.set_point(index, to),
+            Edited::Orbit(..) => move_orbit_handle(self.orbit_mut().unwrap(), index, to),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match &self.edited {
+            Edited::Path(formation) => formation.validate(),
+            Edited::Orbit(_, orbits) => orbits.validate(),
+        }
+    }
+
+    fn spawn_preview(&mut self, commands: &mut Commands, texture: &PreviewTexture) {
+        let (start, preview) = match &self.edited {
+            Edited::Path(formation) => {
+                let follower = formation.follower(&default_win_size());
+                (follower.start(), Preview::Path(follower))
+            }
+            Edited::Orbit(kind, orbits) => {
+                // made the way the game makes them, groups of members included
+                let no_paths = PathFormations(Vec::new());
+                let win_size = default_win_size();
+                match self
+                    .maker
+                    .make(kind, &win_size, &mut self.rng, orbits, &no_paths)
+                {
+                    EnemyMovement::Formation(formation) => {
+                        (formation.start.into(), Preview::Orbit(formation))
+                    }
+                    EnemyMovement::Path(_) => unreachable!(),
+                }
+            }
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: texture.0.clone(),
+                transform: Transform {
+                    translation: start.extend(3.),
+                    scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(preview);
+    }
+}
+
+/// Start range top and bottom, pivot range corners, smallest and largest ellipse corner.
+fn orbit_handles(orbit: &OrbitFormation) -> [Vec2; 6] {
+    let center = pivot_center(orbit);
+    [
+        Vec2::new(orbit.start_x, orbit.start_y.0),
+        Vec2::new(orbit.start_x, orbit.start_y.1),
+        Vec2::new(orbit.pivot_x.0, orbit.pivot_y.0),
+        Vec2::new(orbit.pivot_x.1, orbit.pivot_y.1),
+        center + Vec2::new(orbit.radius_x.0, orbit.radius_y.0),
+        center + Vec2::new(orbit.radius_x.1, orbit.radius_y.1),
+    ]
+}
+
+fn move_orbit_handle(orbit: &mut OrbitFormation, index: usize, to: Vec2) {
+    // radii stay above 0, a whole pixel at least
+    let radius = (to - pivot_center(orbit)).abs().max(Vec2::ONE);
+    match index {
+        0 => (orbit.start_x, orbit.start_y.0) = (to.x.abs(), to.y),
+        1 => (orbit.start_x, orbit.start_y.1) = (to.x.abs(), to.y),
+        2 => (orbit.pivot_x.0, orbit.pivot_y.0) = (to.x, to.y),
+        3 => (orbit.pivot_x.1, orbit.pivot_y.1) = (to.x, to.y),
+        4 => (orbit.radius_x.0, orbit.radius_y.0) = (radius.x, radius.y),
+        _ => (orbit.radius_x.1, orbit.radius_y.1) = (radius.x, radius.y),
+    }
+}
+
+fn pivot_center(orbit: &OrbitFormation) -> Vec2 {
+    Vec2::new(
+        (orbit.pivot_x.0 + orbit.pivot_x.1) / 2.,
+        (orbit.pivot_y.0 + orbit.pivot_y.1) / 2.,
+    )
+}
+
+struct PreviewTexture(Handle<Image>);
+
+/// Drawn again whenever the formation changes.
+#[derive(Component)]
+struct Drawing;
+
+#[derive(Component)]
+enum Preview {
+    Path(PathFollower),
+    Orbit(Formation),
+}
+
+#[derive(Component)]
+struct Status;
+
+fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scale = VIEW_SCALE;
+    commands.spawn_bundle(camera);
+    commands.insert_resource(PreviewTexture(asset_server.load(PREVIEW_SPRITE)));
+
+    // the game's window
+    let (w, h) = (WINDOW_SIZE.0 / 2., WINDOW_SIZE.1 / 2.);
+    let corners = [
+        Vec2::new(-w, -h),
+        Vec2::new(w, -h),
+        Vec2::new(w, h),
+        Vec2::new(-w, h),
+        Vec2::new(-w, -h),
+    ];
+    for pair in corners.windows(2) {
+        spawn_line(
+            &mut commands,
+            pair[0],
+            pair[1],
+            Color::rgb(0.3, 0.3, 0.35),
+            0.,
+        );
+    }
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: FONT_SIZE,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(8.),
+                    top: Val::Px(8.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        )
+        .insert(Status);
+}
+
+/// A straight line as a thin sprite from `a` to `b`.
+fn spawn_line(commands: &mut Commands, a: Vec2, b: Vec2, color: Color, z: f32) -> Entity {
+    let along = b - a;
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(along.length(), LINE_WIDTH * VIEW_SCALE)),
+                ..Default::default()
+            },
+            transform: Transform {
+                translation: ((a + b) / 2.).extend(z),
+                rotation: Quat::from_rotation_z(along.y.atan2(along.x)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id()
+}
+
+/// The mouse in world pixels, which are the file's pixels here.
+fn cursor(windows: &Windows) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let size = Vec2::new(window.width(), window.height());
+    window
+        .cursor_position()
+        .map(|position| (position - size / 2.) * VIEW_SCALE)
+}
+
+fn mouse_system(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+) {
+    if buttons.just_released(MouseButton::Left) {
+        editor.dragging = None;
+    }
+    let cursor = match cursor(&windows) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    let handles = editor.handles();
+    let hovered = handles
+        .iter()
+        .enumerate()
+        .map(|(i, handle)| (i, handle.distance(cursor)))
+        .filter(|&(_, distance)| distance <= PICK_RADIUS * VIEW_SCALE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i);
+
+    if buttons.just_pressed(MouseButton::Left) {
+        editor.dragging = hovered;
+    }
+    if let Some(i) = editor.dragging {
+        if handles[i] != cursor {
+            editor.move_handle(i, cursor);
+            editor.changed();
+        }
+        return;
+    }
+    let shape = match &mut editor.edited {
+        Edited::Path(formation) => &mut formation.shape,
+        Edited::Orbit(..) => return,
+    };
+    let changed = if buttons.just_pressed(MouseButton::Right) {
+        add_point(shape, cursor)
+    } else if keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        hovered.is_some_and(|i| remove_point(shape, i))
+    } else {
+        false
+    };
+    if changed {
+        editor.changed();
+    }
+}
+
+/// Adds `point` at the end of the path. Bezier paths get a whole curve, with its
+/// control points a third of the way in between.
+fn add_point(shape: &mut PathShape, point: Vec2) -> bool {
+    match shape {
+        PathShape::Waypoints(points) | PathShape::CatmullRom(points) => {
+            points.push((point.x, point.y));
+            true
+        }
+        PathShape::Bezier(points) => {
+            let last = Vec2::from(*points.last().unwrap());
+            for t in [1. / 3., 2. / 3., 1.] {
+                let control = last.lerp(point, t);
+                points.push((control.x, control.y));
+            }
+            true
+        }
+        // always two points
+        PathShape::SineDive { .. } | PathShape::Loop { .. } => false,
+    }
+}
+
+/// Removes point `index`, unless the path would be too short. Taking an end point off
+/// a Bezier path takes the control points leading to it too.
+fn remove_point(shape: &mut PathShape, index: usize) -> bool {
+    match shape {
+        PathShape::Waypoints(points) | PathShape::CatmullRom(points) if points.len() > 2 => {
+            points.remove(index);
+            true
+        }
+        PathShape::Bezier(points) if points.len() > 4 && index.is_multiple_of(3) => {
+            let curve = if index == 0 {
+                0..3
+            } else {
+                index - 2..index + 1
+            };
+            points.drain(curve);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn keyboard_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    texture: Res<PreviewTexture>,
+    mut editor: ResMut<Editor>,
+    preview_query: Query<Entity, With<Preview>>,
+) {
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        if keys.just_pressed(KeyCode::S) {
+            save(&mut editor);
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Right) {
+        editor.preview_speed = (editor.preview_speed * 2.).min(PREVIEW_SPEED_MAX);
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        editor.preview_speed = (editor.preview_speed / 2.).max(PREVIEW_SPEED_MIN);
+    }
+    if keys.just_pressed(KeyCode::P) {
+        editor.sending = !editor.sending;
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        editor.spawn_preview(&mut commands, &texture);
+    }
+    if keys.just_pressed(KeyCode::C) {
+        for entity in preview_query.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let changed = match &mut editor.edited {
+        Edited::Path(formation) => path_keys(&keys, formation),
+        Edited::Orbit(..) => orbit_keys(&keys, editor.orbit_mut().unwrap()),
+    };
+    if changed {
+        editor.changed();
+    }
+}
+
+/// True if it changed the path.
+fn path_keys(keys: &Input<KeyCode>, formation: &mut PathFormation) -> bool {
+    let mut changed = false;
+    if keys.just_pressed(KeyCode::Up) {
+        formation.speed += SPEED_STEP;
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::Down) && formation.speed > SPEED_STEP {
+        formation.speed -= SPEED_STEP;
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::E) {
+        formation.end = match formation.end {
+            PathEnd::Stop => PathEnd::Exit,
+            PathEnd::Exit => PathEnd::Repeat,
+            PathEnd::Repeat => PathEnd::Stop,
+        };
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        let shape = match &formation.shape {
+            PathShape::Waypoints(points) => Some(PathShape::CatmullRom(points.clone())),
+            PathShape::CatmullRom(points) => Some(PathShape::Waypoints(points.clone())),
+            _ => None,
+        };
+        if let Some(shape) = shape {
+            formation.shape = shape;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// True if it changed the formation.
+fn orbit_keys(keys: &Input<KeyCode>, orbit: &mut OrbitFormation) -> bool {
+    let mut changed = false;
+    if keys.just_pressed(KeyCode::Up) {
+        orbit.members += 1;
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::Down) && orbit.members > 1 {
+        orbit.members -= 1;
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::A) {
+        orbit.angle = match orbit.angle {
+            Some(_) => None,
+            None => Some(0.),
+        };
+        changed = true;
+    }
+    changed
+}
+
+fn save(editor: &mut Editor) {
+    let result = editor.validate().and_then(|_| {
+        match &editor.edited {
+            Edited::Path(formation) => save_ron(&editor.file, formation, &editor.header),
+            Edited::Orbit(_, orbits) => save_ron(&editor.file, orbits, &editor.header),
+        }
+        .map_err(|err| err.to_string())
+    });
+    match result {
+        Ok(()) => {
+            editor.saved = true;
+            editor.message = format!("saved {}", editor.file.display());
+        }
+        Err(err) => editor.message = format!("not saved: {}", err),
+    }
+}
+
+fn redraw_system(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    drawing_query: Query<Entity, With<Drawing>>,
+    mut preview_query: Query<(Entity, &mut Preview)>,
+) {
+    if !editor.redraw {
+        return;
+    }
+    editor.redraw = false;
+    for entity in drawing_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let (lines, handles) = match &editor.edited {
+        Edited::Path(formation) => path_drawing(&formation.shape),
+        Edited::Orbit(..) => orbit_drawing(editor.orbit().unwrap()),
+    };
+    for (a, b, color) in lines {
+        let z = if color == PATH_COLOR { 1. } else { 0.5 };
+        let line = spawn_line(&mut commands, a, b, color, z);
+        commands.entity(line).insert(Drawing);
+    }
+    for (handle, color) in handles {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(POINT_SIZE * VIEW_SCALE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(handle.extend(2.)),
+                ..Default::default()
+            })
+            .insert(Drawing);
+    }
+
+    match &editor.edited {
+        // the enemies on their way go on from where they are, on the new path
+        Edited::Path(formation) => {
+            for (_, mut preview) in preview_query.iter_mut() {
+                if let Preview::Path(follower) = &mut *preview {
+                    let distance = follower.distance;
+                    *follower = formation.follower(&default_win_size());
+                    follower.distance = distance;
+                }
+            }
+        }
+        // circling ones never leave, new ones show the change
+        Edited::Orbit(..) => {
+            for (entity, _) in preview_query.iter() {
+                commands.entity(entity).despawn();
+            }
+            editor.maker = FormationMaker::default();
+        }
+    }
+}
+
+type Lines = Vec<(Vec2, Vec2, Color)>;
+
+fn path_drawing(shape: &PathShape) -> (Lines, Vec<(Vec2, Color)>) {
+    let mut lines: Lines = shape
+        .sample()
+        .windows(2)
+        .map(|pair| (pair[0], pair[1], PATH_COLOR))
+        .collect();
+    let points = shape.points();
+    if let PathShape::Bezier(_) = shape {
+        // the handles, from each end point to the control points next to it
+        for end in (0..points.len()).step_by(3) {
+            for control in [end.wrapping_sub(1), end + 1] {
+                if let Some(&control) = points.get(control) {
+                    lines.push((points[end], control, HANDLE_LINE_COLOR));
+                }
+            }
+        }
+    }
+    let handles = points
+        .into_iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let color = match (i, shape) {
+                (0, _) => START_COLOR,
+                (i, PathShape::Bezier(_)) if !i.is_multiple_of(3) => CONTROL_COLOR,
+                _ => POINT_COLOR,
+            };
+            (point, color)
+        })
+        .collect();
+    (lines, handles)
+}
+
+fn orbit_drawing(orbit: &OrbitFormation) -> (Lines, Vec<(Vec2, Color)>) {
+    let mut lines = Lines::new();
+    // where they come in, on both sides
+    for side in [1., -1.] {
+        let x = side * orbit.start_x;
+        lines.push((
+            Vec2::new(x, orbit.start_y.0),
+            Vec2::new(x, orbit.start_y.1),
+            START_COLOR,
+        ));
+    }
+    // where the pivot can be
+    let (from, to) = (
+        Vec2::new(orbit.pivot_x.0, orbit.pivot_y.0),
+        Vec2::new(orbit.pivot_x.1, orbit.pivot_y.1),
+    );
+    let corners = [
+        from,
+        Vec2::new(to.x, from.y),
+        to,
+        Vec2::new(from.x, to.y),
+        from,
+    ];
+    for pair in corners.windows(2) {
+        lines.push((pair[0], pair[1], PIVOT_COLOR));
+    }
+    // the smallest and the largest ellipse, around the middle of that
+    let center = pivot_center(orbit);
+    for radius in [
+        Vec2::new(orbit.radius_x.0, orbit.radius_y.0),
+        Vec2::new(orbit.radius_x.1, orbit.radius_y.1),
+    ] {
+        let point = |i: usize| {
+            let angle = TAU * i as f32 / ELLIPSE_SEGMENTS as f32;
+            center + Vec2::new(angle.cos(), angle.sin()) * radius
+        };
+        for i in 0..ELLIPSE_SEGMENTS {
+            lines.push((point(i), point(i + 1), CONTROL_COLOR));
+        }
+    }
+
+    let colors = [
+        START_COLOR,
+        START_COLOR,
+        PIVOT_COLOR,
+        PIVOT_COLOR,
+        CONTROL_COLOR,
+        CONTROL_COLOR,
+    ];
+    let handles = orbit_handles(orbit).into_iter().zip(colors).collect();
+    (lines, handles)
+}
+
+fn preview_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    texture: Res<PreviewTexture>,
+    mut editor: ResMut<Editor>,
+    mut query: Query<(Entity, &mut Transform, &mut Preview)>,
+) {
+    let delta = time.delta_seconds() * editor.preview_speed;
+    if editor.sending
+        && editor.send_timer.tick(time.delta()).just_finished()
+        && query.iter().count() < PREVIEWS_MAX
+    {
+        editor.spawn_preview(&mut commands, &texture);
+    }
+    editor.ticks += delta / TIME_STEP;
+    let ticks = editor.ticks.floor();
+    editor.ticks -= ticks;
+
+    let (w, h) = (
+        (WINDOW_SIZE.0 / 2. + EDITOR_MARGIN) * VIEW_SCALE,
+        (WINDOW_SIZE.1 / 2. + EDITOR_MARGIN) * VIEW_SCALE,
+    );
+    for (entity, mut transform, mut preview) in query.iter_mut() {
+        match &mut *preview {
+            Preview::Path(follower) => {
+                follower.advance(delta);
+                let (position, _) = follower.at(follower.distance);
+                transform.translation = position.extend(transform.translation.z);
+                let gone = match follower.end() {
+                    PathEnd::Stop => follower.finished(),
+                    PathEnd::Exit => {
+                        follower.finished() && (position.x.abs() > w || position.y.abs() > h)
+                    }
+                    PathEnd::Repeat => false,
+                };
+                if gone {
+                    commands.entity(entity).despawn();
+                }
+            }
+            Preview::Orbit(formation) => {
+                let translation = &mut transform.translation;
+                for _ in 0..ticks as u32 {
+                    (translation.x, translation.y) = formation.step((translation.x, translation.y));
+                }
+            }
+        }
+    }
+}
+
+fn status_system(editor: Res<Editor>, mut query: Query<&mut Text, With<Status>>) {
+    let name = editor
+        .file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let details = match &editor.edited {
+        Edited::Path(formation) => {
+            let shape = match formation.shape {
+                PathShape::Waypoints(_) => "Waypoints",
+                PathShape::CatmullRom(_) => "CatmullRom",
+                PathShape::Bezier(_) => "Bezier",
+                PathShape::SineDive { .. } => "SineDive",
+                PathShape::Loop { .. } => "Loop",
+            };
+            format!(
+                "{}, {} points, {:?}\nspeed {} px/s",
+                shape,
+                formation.shape.points().len(),
+                formation.end,
+                formation.speed,
+            )
+        }
+        Edited::Orbit(kind, _) => {
+            let orbit = editor.orbit().unwrap();
+            format!(
+                "{:?}, {} members\nstarts circling {}",
+                kind,
+                orbit.members,
+                match orbit.angle {
+                    Some(angle) => format!("at angle {}", angle),
+                    None => "towards the start".to_string(),
+                },
+            )
+        }
+    };
+    let status = format!(
+        "{}{}\n{}\npreview x{}{}\n{}",
+        name,
+        if editor.saved { "" } else { " *" },
+        details,
+        editor.preview_speed,
+        if editor.sending { "" } else { ", paused" },
+        editor.message,
+    );
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::{AlphaMask, Hitbox};
+use crate::data::{asset_path, load_ron_dir, DataError};
+use crate::enemy::archetype::EnemyWeapon;
+use crate::pattern::Patterns;
+
+/// One boss, read from a `.ron` file in `assets/bosses/`. The file name is its name.
+///
+/// Offsets and movement are in world pixels around the boss's center, sizes and hitboxes
+/// in sprite pixels like everywhere else.
+#[derive(Clone, Deserialize)]
+pub struct BossKind {
+    #[serde(skip)]
+    pub name: String,
+    /// the core, the only part that has to be destroyed
+    pub hull: PartSprite,
+    pub size: (f32, f32),
+    /// see `Hitbox::or_rect`
+    #[serde(default)]
+    pub hitbox: Option<Hitbox>,
+    pub health: u32,
+    pub score: u32,
+    /// decoration, drawn behind the hull
+    #[serde(default)]
+    pub parts: Vec<PartSprite>,
+    #[serde(default)]
+    pub turrets: Vec<TurretKind>,
+    pub entry: Entry,
+    /// in order, each one starting once health drops to its `from_health`
+    pub phases: Vec<Phase>,
+    pub death: Death,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct PartSprite {
+    pub sprite: String,
+    #[serde(default)]
+    pub offset: (f32, f32),
+    /// on top of `SPRITE_SCALE`
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+/// A part that can be shot off on its own, and fires on its own while the boss fights.
+#[derive(Clone, Deserialize)]
+pub struct TurretKind {
+    pub part: PartSprite,
+    pub size: (f32, f32),
+    /// see `Hitbox::or_rect`
+    #[serde(default)]
+    pub hitbox: Option<Hitbox>,
+    pub health: u32,
+    pub score: u32,
+    pub weapon: EnemyWeapon,
+}
+
+/// Flies in from above the screen and stops `from_top` pixels below its top edge.
+/// Cannot be hurt until it gets there.
+#[derive(Clone, Copy, Deserialize)]
+pub struct Entry {
+    pub from_top: f32,
+    /// pixels per second
+    pub speed: f32,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Phase {
+    /// health fraction, 0 to 1, the first phase has 1
+    pub from_health: f32,
+    pub movement: BossMovement,
+    /// each with its own pattern and fire rate
+    #[serde(default)]
+    pub attacks: Vec<EnemyWeapon>,
+}
+
+/// Where the boss wants to be, around the point its entry ended at. It moves there at
+/// most `BOSS_MAX_SPEED` pixels per second, so phases blend into each other.
+#[derive(Clone, Copy, Deserialize)]
+pub enum BossMovement {
+    Hold,
+    /// side to side, `period` seconds for a full swing
+    Sway {
+        width: f32,
+        period: f32,
+    },
+    /// a figure eight, `period` seconds for a full loop
+    Figure8 {
+        width: f32,
+        height: f32,
+        period: f32,
+    },
+    /// keeps above the player, at `speed` pixels per second
+    Follow {
+        speed: f32,
+    },
+}
+
+impl BossMovement {
+    /// `time` since the phase started.
+    pub fn target(&self, anchor: Vec2, time: f32, player: Option<Vec2>) -> Vec2 {
+        match *self {
+            BossMovement::Hold => anchor,
+            BossMovement::Sway { width, period } => {
+                anchor + Vec2::new(width * (TAU * time / period).sin(), 0.)
+            }
+            BossMovement::Figure8 {
+                width,
+                height,
+                period,
+            } => {
+                let angle = TAU * time / period;
+                anchor + Vec2::new(width * angle.sin(), height * (2. * angle).sin() / 2.)
+            }
+            BossMovement::Follow { .. } => {
+                Vec2::new(player.map_or(anchor.x, |player| player.x), anchor.y)
+            }
+        }
+    }
+
+    /// Pixels per second, if slower than `BOSS_MAX_SPEED`.
+    pub fn speed(&self) -> Option<f32> {
+        match *self {
+            BossMovement::Follow { speed } => Some(speed),
+            _ => None,
+        }
+    }
+}
+
+/// Explosions all over the hull every `interval` seconds for `duration`, then one of
+/// `final_scale` times the usual size and it is gone.
+#[derive(Clone, Copy, Deserialize)]
+pub struct Death {
+    pub duration: f32,
+    pub interval: f32,
+    pub final_scale: f32,
+}
+
+impl TurretKind {
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox::or_rect(&self.hitbox, self.size)
+    }
+}
+
+impl BossKind {
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox::or_rect(&self.hitbox, self.size)
+    }
+
+    /// Index of the phase for the remaining health fraction.
+    pub fn phase_for(&self, health: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| health <= phase.from_health)
+            .unwrap_or(0)
+    }
+
+    fn weapons_mut(&mut self) -> impl Iterator<Item = &mut EnemyWeapon> {
+        let phases = self
+            .phases
+            .iter_mut()
+            .flat_map(|phase| phase.attacks.iter_mut());
+        let turrets = self.turrets.iter_mut().map(|turret| &mut turret.weapon);
+        phases.chain(turrets)
+    }
+
+    fn validate(&self, patterns: &Patterns) -> Result<(), String> {
+        if self.phases.is_empty() {
+            return Err("no phases".to_string());
+        }
+        if self.phases[0].from_health != 1. {
+            return Err("the first phase has to start at from_health 1".to_string());
+        }
+        // `phase_for` looks for the last phase it is under, they have to go down
+        for pair in self.phases.windows(2) {
+            let (from, to) = (pair[0].from_health, pair[1].from_health);
+            if !(0. < to && to < from) {
+                return Err(format!(
+                    "from_health {} after {}, it has to go down and stay above 0",
+                    to, from
+                ));
+            }
+        }
+        let weapons = self
+            .phases
+            .iter()
+            .flat_map(|phase| phase.attacks.iter())
+            .chain(self.turrets.iter().map(|turret| &turret.weapon));
+        for weapon in weapons {
+            weapon.validate(patterns)?;
+        }
+        if self.death.interval <= 0. {
+            return Err("death interval has to be above 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// All bosses, indexed by `Boss::kind`.
+pub struct BossKinds(pub Vec<BossKind>);
+
+impl BossKinds {
+    pub fn load(dir: &str, patterns: &Patterns) -> Result<Self, DataError> {
+        let kinds = load_ron_dir(&asset_path(dir), |kind: &mut BossKind| {
+            kind.validate(patterns)?;
+            for weapon in kind.weapons_mut() {
+                weapon.mask = AlphaMask::load_or_warn(&weapon.sprite);
+            }
+            Ok(())
+        })?
+        .into_iter()
+        .map(|(name, kind)| BossKind { name, ..kind })
+        .collect();
+        Ok(Self(kinds))
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|kind| kind.name == name)
+    }
+
+    /// Hull, parts, turrets and weapons alike, as in `EnemyArchetypes::load_textures`.
+    pub fn load_textures(&mut self, asset_server: &AssetServer) {
+        for kind in self.0.iter_mut() {
+            kind.hull.texture = asset_server.load(&kind.hull.sprite);
+            for part in kind.parts.iter_mut() {
+                part.texture = asset_server.load(&part.sprite);
+            }
+            for turret in kind.turrets.iter_mut() {
+                turret.part.texture = asset_server.load(&turret.part.sprite);
+            }
+            for weapon in kind.weapons_mut() {
+                weapon.texture = asset_server.load(&weapon.sprite);
+            }
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use rand::Rng;
+
+use crate::collision::{CollisionEvent, CollisionLayer, SpentProjectiles};
+use crate::components::{
+    Damage, ExplosionScale, ExplosionToSpawn, FromPlayer, Health, HitFlash, Laser, Player,
+    SpriteSize,
+};
+use crate::events::{BombUsed, BossPartDestroyed};
+use crate::pattern::{fire_pattern, BulletQueue, Gun, Patterns, WeaponRef};
+use crate::rng::GameRng;
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{despawn_with, AppState, GameStep, InGameStep};
+use crate::{WinSize, BOMB_DAMAGE, BOSS_BAR_SIZE, BOSS_DIR, BOSS_MAX_SPEED, SPRITE_SCALE};
+
+use self::kind::{BossKind, BossKinds, PartSprite, TurretKind};
+
+pub mod kind;
+
+/// Bosses: entry, phases, turrets, health bar and the long death.
+/// Waves spawn them with `boss: Some("name")`.
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        let kinds = BossKinds::load(BOSS_DIR, app.world.resource::<Patterns>())
+            .unwrap_or_else(|err| panic!("could not load bosses: {}", err));
+        app.insert_resource(kinds)
+            .add_event::<BossPartDestroyed>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_system(boss_phase_system.in_step(GameStep::Boss))
+                    .with_system(
+                        boss_movement_system
+                            .in_step(GameStep::Boss)
+                            .after(boss_phase_system),
+                    )
+                    .with_system(
+                        boss_follow_system
+                            .in_step(GameStep::Boss)
+                            .after(boss_movement_system),
+                    )
+                    .with_system(
+                        boss_fire_system
+                            .in_step(GameStep::Boss)
+                            .after(boss_follow_system),
+                    )
+                    .with_system(
+                        boss_death_system
+                            .in_step(GameStep::Boss)
+                            .after(boss_fire_system),
+                    )
+                    .with_system(boss_hit_system.in_step(GameStep::BossHits)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(despawn_with::<Boss>)
+                    .with_system(despawn_with::<BossPart>)
+                    .with_system(despawn_with::<BossHealthBar>),
+            );
+    }
+}
+
+/// The core of a boss. Its parts, turrets and health bar are separate entities
+/// pointing back at it, collisions and interpolation only look at the local `Transform`.
+#[derive(Component)]
+pub struct Boss {
+    /// index into `BossKinds`
+    pub kind: usize,
+    pub phase: usize,
+    pub state: BossState,
+    /// where the entry ends, phase movement is around it
+    anchor: Vec2,
+    /// seconds since the phase started
+    phase_time: f32,
+    /// one per attack of the current phase, turrets carry theirs as a component
+    attacks: Vec<Gun>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BossState {
+    /// flying in, cannot be hurt
+    Entering,
+    Fighting,
+    /// blowing up, `next` is when the next explosion goes off
+    Dying {
+        elapsed: f32,
+        next: f32,
+    },
+}
+
+/// Anything that moves along with a boss, `offset` in world pixels from its center.
+#[derive(Component)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub offset: Vec2,
+}
+
+/// A part that can be destroyed, `index` into `BossKind::turrets`.
+#[derive(Component)]
+pub struct Turret {
+    pub boss: Entity,
+    pub index: usize,
+}
+
+/// The bar along the top of the screen, the back and the `fill` on top of it.
+#[derive(Component)]
+pub struct BossHealthBar {
+    pub boss: Entity,
+    pub fill: bool,
+}
+
+fn part_sprite(part: &PartSprite, position: Vec3) -> SpriteBundle {
+    let scale = SPRITE_SCALE * part.scale;
+    SpriteBundle {
+        texture: part.texture.clone(),
+        sprite: Sprite {
+            flip_x: part.flip_x,
+            flip_y: part.flip_y,
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position,
+            scale: Vec3::new(scale, scale, 1.),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Spawns the boss above the screen, from where it flies in.
+pub fn spawn_boss(
+    commands: &mut Commands,
+    index: usize,
+    kind: &BossKind,
+    win_size: &WinSize,
+) -> Entity {
+    let height = kind.size.1 * SPRITE_SCALE * kind.hull.scale;
+    let start = Vec2::new(0., win_size.h / 2. + height);
+    let anchor = Vec2::new(0., win_size.h / 2. - kind.entry.from_top);
+
+    let boss = commands
+        .spawn_bundle(part_sprite(&kind.hull, start.extend(10.)))
+        .insert(Boss {
+            kind: index,
+            phase: 0,
+            state: BossState::Entering,
+            anchor,
+            phase_time: 0.,
+            attacks: kind.phases[0].attacks.iter().map(Gun::new).collect(),
+        })
+        .insert(SpriteSize::from(kind.size))
+        .insert(kind.hitbox())
+        .insert(CollisionLayer::Enemy)
+        .insert(Health::new(kind.health))
+        .insert(BulletQueue::default())
+        .id();
+
+    for part in kind.parts.iter() {
+        let offset = Vec2::from(part.offset);
+        commands
+            .spawn_bundle(part_sprite(part, (start + offset).extend(9.)))
+            .insert(BossPart { boss, offset });
+    }
+    for (i, turret) in kind.turrets.iter().enumerate() {
+        spawn_turret(commands, boss, i, turret, start);
+    }
+
+    let bar_position = Vec3::new(-BOSS_BAR_SIZE.0 / 2., win_size.h / 2. - 50., 20.);
+    for (fill, color) in [
+        (false, Color::rgba(0., 0., 0., 0.6)),
+        (true, Color::rgb(0.9, 0.1, 0.1)),
+    ] {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::from(BOSS_BAR_SIZE)),
+                    anchor: Anchor::CenterLeft,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(bar_position + Vec3::Z * fill as u8 as f32),
+                ..Default::default()
+            })
+            .insert(BossHealthBar { boss, fill });
+    }
+    boss
+}
+
+fn spawn_turret(
+    commands: &mut Commands,
+    boss: Entity,
+    index: usize,
+    turret: &TurretKind,
+    start: Vec2,
+) {
+    let offset = Vec2::from(turret.part.offset);
+    commands
+        .spawn_bundle(part_sprite(&turret.part, (start + offset).extend(11.)))
+        .insert(BossPart { boss, offset })
+        .insert(Turret { boss, index })
+        .insert(Gun::new(&turret.weapon))
+        .insert(BulletQueue::default())
+        .insert(SpriteSize::from(turret.size))
+        .insert(turret.hitbox())
+        .insert(CollisionLayer::Enemy)
+        .insert(Health::new(turret.health));
+}
+
+fn boss_phase_system(kinds: Res<BossKinds>, mut query: Query<(&mut Boss, &Health)>) {
+    for (mut boss, health) in query.iter_mut() {
+        if boss.state != BossState::Fighting {
+            continue;
+        }
+        let kind = &kinds.0[boss.kind];
+        let phase = kind.phase_for(health.current as f32 / health.max as f32);
+        // healing never goes back a phase
+        if phase > boss.phase {
+            boss.phase = phase;
+            boss.phase_time = 0.;
+            boss.attacks = kind.phases[phase].attacks.iter().map(Gun::new).collect();
+        }
+    }
+}
+
+fn boss_movement_system(
+    kinds: Res<BossKinds>,
+    sim_time: Res<SimTime>,
+    player_query: Query<&Transform, (With<Player>, Without<Boss>)>,
+    mut query: Query<(&mut Boss, &mut Transform)>,
+) {
+    let delta = sim_time.delta_seconds();
+    let player = player_query.get_single().ok().map(|tf| tf.translation.xy());
+    for (mut boss, mut transform) in query.iter_mut() {
+        let kind = &kinds.0[boss.kind];
+        let (target, speed) = match boss.state {
+            BossState::Entering => (boss.anchor, kind.entry.speed),
+            BossState::Fighting => {
+                boss.phase_time += delta;
+                let movement = kind.phases[boss.phase].movement;
+                let target = movement.target(boss.anchor, boss.phase_time, player);
+                (target, movement.speed().unwrap_or(BOSS_MAX_SPEED))
+            }
+            BossState::Dying { .. } => continue,
+        };
+        let position = transform.translation.xy();
+        let step = (target - position).clamp_length_max(speed * delta);
+        transform.translation += step.extend(0.);
+        if boss.state == BossState::Entering && position + step == boss.anchor {
+            boss.state = BossState::Fighting;
+        }
+    }
+}
+
+fn boss_follow_system(
+    win_size: Res<WinSize>,
+    boss_query: Query<(&Transform, &Health), With<Boss>>,
+    mut part_query: Query<(&BossPart, &mut Transform), Without<Boss>>,
+    mut bar_query: Query<
+        (&BossHealthBar, &mut Sprite, &mut Transform),
+        (Without<Boss>, Without<BossPart>),
+    >,
+) {
+    for (part, mut transform) in part_query.iter_mut() {
+        if let Ok((boss_tf, _)) = boss_query.get(part.boss) {
+            let z = transform.translation.z;
+            transform.translation = (boss_tf.translation.xy() + part.offset).extend(z);
+        }
+    }
+    for (bar, mut sprite, mut transform) in bar_query.iter_mut() {
+        let (_, health) = match boss_query.get(bar.boss) {
+            Ok(boss) => boss,
+            Err(_) => continue,
+        };
+        transform.translation.y = win_size.h / 2. - 50.;
+        if bar.fill {
+            let fraction = health.current as f32 / health.max as f32;
+            sprite.custom_size = Some(Vec2::new(BOSS_BAR_SIZE.0 * fraction, BOSS_BAR_SIZE.1));
+        }
+    }
+}
+
+fn boss_fire_system(
+    mut commands: Commands,
+    kinds: Res<BossKinds>,
+    patterns: Res<Patterns>,
+    sim_time: Res<SimTime>,
+    player_query: Query<&Transform, With<Player>>,
+    mut boss_query: Query<(&mut Boss, &mut BulletQueue, &Transform), Without<Player>>,
+    mut turret_query: Query<
+        (&Turret, &mut Gun, &mut BulletQueue, &Transform),
+        (Without<Boss>, Without<Player>),
+    >,
+) {
+    let player = player_query.get_single().ok().map(|tf| tf.translation.xy());
+    for (mut boss, mut queue, transform) in boss_query.iter_mut() {
+        if boss.state != BossState::Fighting {
+            continue;
+        }
+        let boss = &mut *boss;
+        let attacks = &kinds.0[boss.kind].phases[boss.phase].attacks;
+        for (attack, (weapon, gun)) in attacks.iter().zip(boss.attacks.iter_mut()).enumerate() {
+            if let Some(volley) = gun.trigger(sim_time.delta()) {
+                let source = WeaponRef::BossAttack {
+                    kind: boss.kind,
+                    phase: boss.phase,
+                    attack,
+                };
+                fire_pattern(
+                    &mut commands,
+                    &patterns,
+                    weapon,
+                    source,
+                    volley,
+                    transform,
+                    Vec2::ZERO,
+                    player,
+                    &mut queue,
+                );
+            }
+        }
+    }
+    for (turret, mut gun, mut queue, transform) in turret_query.iter_mut() {
+        let boss = match boss_query.get(turret.boss) {
+            Ok((boss, _, _)) => boss,
+            Err(_) => continue,
+        };
+        if boss.state != BossState::Fighting {
+            continue;
+        }
+        let weapon = &kinds.0[boss.kind].turrets[turret.index].weapon;
+        if let Some(volley) = gun.trigger(sim_time.delta()) {
+            let source = WeaponRef::Turret {
+                kind: boss.kind,
+                turret: turret.index,
+            };
+            fire_pattern(
+                &mut commands,
+                &patterns,
+                weapon,
+                source,
+                volley,
+                transform,
+                Vec2::ZERO,
+                player,
+                &mut queue,
+            );
+        }
+    }
+}
+
+fn boss_death_system(
+    mut commands: Commands,
+    kinds: Res<BossKinds>,
+    sim_time: Res<SimTime>,
+    mut rng: ResMut<GameRng>,
+    mut boss_query: Query<(Entity, &mut Boss, &Transform)>,
+    part_query: Query<(Entity, &BossPart)>,
+    bar_query: Query<(Entity, &BossHealthBar)>,
+) {
+    for (entity, mut boss, transform) in boss_query.iter_mut() {
+        let (mut elapsed, mut next) = match boss.state {
+            BossState::Dying { elapsed, next } => (elapsed, next),
+            _ => continue,
+        };
+        let kind = &kinds.0[boss.kind];
+        elapsed += sim_time.delta_seconds();
+
+        // small ones all over the hull
+        let half = Vec2::from(kind.size) * SPRITE_SCALE * kind.hull.scale / 2.;
+        while next <= elapsed && next < kind.death.duration {
+            let offset = Vec2::new(
+                rng.gen_range(-half.x..half.x),
+                rng.gen_range(-half.y..half.y),
+            );
+            commands
+                .spawn()
+                .insert(ExplosionToSpawn(transform.translation + offset.extend(1.)));
+            next += kind.death.interval;
+        }
+        boss.state = BossState::Dying { elapsed, next };
+        if elapsed < kind.death.duration {
+            continue;
+        }
+
+        // and the big one
+        commands
+            .spawn()
+            .insert(ExplosionToSpawn(transform.translation + Vec3::Z))
+            .insert(ExplosionScale(kind.death.final_scale));
+        commands.entity(entity).despawn();
+        for (part_entity, part) in part_query.iter() {
+            if part.boss == entity {
+                commands.entity(part_entity).despawn();
+            }
+        }
+        for (bar_entity, bar) in bar_query.iter() {
+            if bar.boss == entity {
+                commands.entity(bar_entity).despawn();
+            }
+        }
+    }
+}
+
+/// Player lasers and bombs against the core and the turrets.
+fn boss_hit_system(
+    mut commands: Commands,
+    kinds: Res<BossKinds>,
+    win_size: Res<WinSize>,
+    mut destroyed_events: EventWriter<BossPartDestroyed>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut spent: ResMut<SpentProjectiles>,
+    mut bomb_events: EventReader<BombUsed>,
+    laser_query: Query<&Damage, (With<Laser>, With<FromPlayer>)>,
+    mut boss_query: Query<(Entity, &mut Boss, &mut Health, &Transform)>,
+    mut turret_query: Query<(Entity, &Turret, &mut Health, &Transform), Without<Boss>>,
+) {
+    let state_of = |boss_query: &Query<(Entity, &mut Boss, &mut Health, &Transform)>, boss| {
+        boss_query.get(boss).map(|(_, boss, _, _)| boss.state).ok()
+    };
+
+    // (boss or turret, damage)
+    let mut hits: Vec<(Entity, u32)> = Vec::new();
+    for collision in collision_events.iter() {
+        let (laser_entity, target) = (collision.a, collision.b);
+        // one laser touching an enemy too hit that in `GameStep::PlayerHits`
+        if spent.0.contains(&laser_entity) {
+            continue;
+        }
+        let damage = match laser_query.get(laser_entity) {
+            Ok(damage) => damage.0,
+            Err(_) => continue,
+        };
+        let boss = match turret_query.get(target) {
+            Ok((_, turret, _, _)) => turret.boss,
+            Err(_) => target,
+        };
+        match state_of(&boss_query, boss) {
+            Some(BossState::Fighting) => hits.push((target, damage)),
+            // the shield is up while it flies in
+            Some(BossState::Entering) => {}
+            // lasers fly through the wreck
+            Some(BossState::Dying { .. }) | None => continue,
+        }
+        commands.entity(laser_entity).despawn();
+        spent.0.insert(laser_entity);
+    }
+
+    if bomb_events.iter().count() > 0 {
+        let on_screen = |tf: &Transform| {
+            tf.translation.x.abs() <= win_size.w / 2. && tf.translation.y.abs() <= win_size.h / 2.
+        };
+        for (entity, boss, _, tf) in boss_query.iter() {
+            if boss.state == BossState::Fighting && on_screen(tf) {
+                hits.push((entity, BOMB_DAMAGE));
+            }
+        }
+        for (entity, turret, _, tf) in turret_query.iter() {
+            if state_of(&boss_query, turret.boss) == Some(BossState::Fighting) && on_screen(tf) {
+                hits.push((entity, BOMB_DAMAGE));
+            }
+        }
+    }
+
+    for (target, damage) in hits {
+        if let Ok((_, turret, mut health, tf)) = turret_query.get_mut(target) {
+            // already gone, along with the core or to an earlier hit
+            if health.current == 0 {
+                continue;
+            }
+            if !health.take(damage) {
+                commands.entity(target).insert(HitFlash::default());
+                continue;
+            }
+            commands.entity(target).despawn();
+            commands.spawn().insert(ExplosionToSpawn(tf.translation));
+            let kind = match boss_query.get(turret.boss) {
+                Ok((_, boss, _, _)) => boss.kind,
+                Err(_) => continue,
+            };
+            destroyed_events.send(BossPartDestroyed {
+                position: tf.translation,
+                score: kinds.0[kind].turrets[turret.index].score,
+            });
+            continue;
+        }
+
+        let (_, mut boss, mut health, tf) = match boss_query.get_mut(target) {
+            Ok(boss) => boss,
+            Err(_) => continue,
+        };
+        if boss.state != BossState::Fighting {
+            continue;
+        }
+        if !health.take(damage) {
+            commands.entity(target).insert(HitFlash::default());
+            continue;
+        }
+        boss.state = BossState::Dying {
+            elapsed: 0.,
+            next: 0.,
+        };
+        // lasers fly through the wreck, and stream bullets still queued are dropped
+        commands
+            .entity(target)
+            .remove::<CollisionLayer>()
+            .remove::<BulletQueue>();
+        destroyed_events.send(BossPartDestroyed {
+            position: tf.translation,
+            score: kinds.0[boss.kind].score,
+        });
+        // the turrets go down with it
+        for (turret_entity, turret, mut health, turret_tf) in turret_query.iter_mut() {
+            if turret.boss == target && health.current > 0 {
+                health.current = 0;
+                commands.entity(turret_entity).despawn();
+                commands
+                    .spawn()
+                    .insert(ExplosionToSpawn(turret_tf.translation));
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::sync::Arc;
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy::render::texture::{CompressedImageFormats, ImageType};
+
+use super::turned_half_size;
+use crate::data::{asset_path, DataError};
+
+/// Pixels below this alpha do not collide.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Which pixels of a sprite are solid, one bit each, read from the PNG's alpha channel.
+/// Cheap to clone, every laser of a kind shares the same bits.
+#[derive(Component, Clone, Debug)]
+pub struct AlphaMask {
+    pub width: u32,
+    pub height: u32,
+    solid: usize,
+    bits: Arc<Vec<u64>>,
+}
+
+/// Collisions involving this entity test the `AlphaMask`s of both sprites,
+/// when both have one, instead of their `Hitbox`es.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PixelPerfect;
+
+impl AlphaMask {
+    /// Reads `assets/<sprite>`, without going through the `AssetServer` so it works headless too.
+    pub fn load(sprite: &str) -> Result<Self, DataError> {
+        let path = asset_path(sprite);
+        let bytes = fs::read(&path).map_err(|err| DataError::Io(path.clone(), err))?;
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let image = Image::from_buffer(
+            &bytes,
+            ImageType::Extension(&extension),
+            CompressedImageFormats::NONE,
+            true,
+        )
+        .map_err(|err| DataError::Image(path.clone(), err.to_string()))?;
+
+        let size = image.size();
+        let (width, height) = (size.x as u32, size.y as u32);
+        let pixel_size = image.texture_descriptor.format.describe().block_size as usize;
+        let mut bits = vec![0u64; ((width * height) as usize).div_ceil(64)];
+        for (i, pixel) in image.data.chunks_exact(pixel_size).enumerate() {
+            // only RGBA and grey+alpha images have alpha, anything else is solid
+            let alpha = match pixel_size {
+                4 => pixel[3],
+                2 => pixel[1],
+                _ => u8::MAX,
+            };
+            if alpha >= ALPHA_THRESHOLD {
+                bits[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            solid: bits.iter().map(|word| word.count_ones() as usize).sum(),
+            bits: Arc::new(bits),
+        })
+    }
+
+    /// Same as `load`, but a sprite without a mask only falls back to its hitbox.
+    pub fn load_or_warn(sprite: &str) -> Option<Self> {
+        Self::load(sprite)
+            .map_err(|err| warn!("no alpha mask, using the hitbox: {}", err))
+            .ok()
+    }
+
+    /// Pixel coordinates, `y` down as in the image.
+    pub fn get(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        let i = (y as u32 * self.width + x as u32) as usize;
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Whether any solid pixel of one sprite lies on a solid pixel of the other.
+    /// Both sprites are drawn at their image size, centered on their transform.
+    pub fn overlaps(&self, tf: &Transform, other: &AlphaMask, other_tf: &Transform) -> bool {
+        // AABB pre-check first, most pairs end here. Bullets fly turned along their way.
+        let bounds = |mask: &AlphaMask, tf: &Transform| {
+            let half = turned_half_size(mask.half_size() * 2., tf);
+            (tf.translation.xy() - half, tf.translation.xy() + half)
+        };
+        let ((a_min, a_max), (b_min, b_max)) = (bounds(self, tf), bounds(other, other_tf));
+        if a_min.x > b_max.x || b_min.x > a_max.x || a_min.y > b_max.y || b_min.y > a_max.y {
+            return false;
+        }
+
+        // walk the smaller sprite and look each of its pixels up in the other one
+        let (walk, walk_tf, look, look_tf) = if self.solid <= other.solid {
+            (self, tf, other, other_tf)
+        } else {
+            (other, other_tf, self, tf)
+        };
+        let to_look = look_tf.compute_matrix().inverse() * walk_tf.compute_matrix();
+        let (walk_half, look_half) = (walk.half_size(), look.half_size());
+        for y in 0..walk.height as i32 {
+            for x in 0..walk.width as i32 {
+                if !walk.get(x, y) {
+                    continue;
+                }
+                let local = Vec3::new(
+                    x as f32 + 0.5 - walk_half.x,
+                    walk_half.y - y as f32 - 0.5,
+                    0.,
+                );
+                let p = to_look.transform_point3(local);
+                if look.get(
+                    (p.x + look_half.x).floor() as i32,
+                    (look_half.y - p.y).floor() as i32,
+                ) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn half_size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) / 2.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn solid(width: u32, height: u32) -> AlphaMask {
+        let pixels = (width * height) as usize;
+        let mut bits = vec![0u64; pixels.div_ceil(64)];
+        for i in 0..pixels {
+            bits[i / 64] |= 1 << (i % 64);
+        }
+        AlphaMask {
+            width,
+            height,
+            solid: pixels,
+            bits: Arc::new(bits),
+        }
+    }
+
+    #[test]
+    fn turned_sprites_overlap_past_their_upright_bounds() {
+        let (bullet, ship) = (solid(4, 40), solid(4, 4));
+        let ship_tf = Transform::from_xyz(15., 0., 0.);
+        // upright, the bullet is far too thin to reach
+        assert!(!bullet.overlaps(&Transform::default(), &ship, &ship_tf));
+        // flying sideways it does
+        let sideways = Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2));
+        assert!(bullet.overlaps(&sideways, &ship, &ship_tf));
+        assert!(ship.overlaps(&ship_tf, &bullet, &sideways));
+    }
+}
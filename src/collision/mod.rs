@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+
+use crate::components::SpriteSize;
+use crate::simulation::SimulationStage;
+use crate::state::{GameStep, InGameStep};
+use crate::COLLISION_CELL_SIZE;
+
+use self::spatial::SpatialHash;
+
+pub use self::mask::{AlphaMask, PixelPerfect};
+pub use self::shape::{Hitbox, Shape};
+
+pub mod mask;
+pub mod shape;
+pub mod spatial;
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CollisionEvent>()
+            .insert_resource(CollisionIndex::default())
+            .insert_resource(SpentProjectiles::default())
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_system(
+                        collision_index_system
+                            .in_step(GameStep::Collision)
+                            .label(CollisionIndexSystem),
+                    )
+                    .with_system(
+                        collision_detection_system
+                            .in_step(GameStep::Collision)
+                            .label(CollisionSystem)
+                            .after(CollisionIndexSystem),
+                    ),
+            );
+    }
+}
+
+/// Systems using `CollisionIndex` run `.after(CollisionIndexSystem)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct CollisionIndexSystem;
+
+/// Systems reacting to `CollisionEvent`s run `.after(CollisionSystem)` to see them the same frame.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct CollisionSystem;
+
+/// What an entity is, as far as collisions go. Only some layer pairs collide, see `interacts`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CollisionLayer {
+    Player,
+    PlayerProjectile,
+    Enemy,
+    EnemyProjectile,
+    Pickup,
+}
+
+impl CollisionLayer {
+    pub fn interacts(self, other: CollisionLayer) -> bool {
+        use CollisionLayer::*;
+        matches!(
+            (self.min(other), self.max(other)),
+            (Player, EnemyProjectile) | (Player, Pickup) | (PlayerProjectile, Enemy)
+        )
+    }
+}
+
+/// Broad phase shared by every collision query: all colliding entities
+/// by their sprite bounds (`Transform` + `SpriteSize`), rebuilt every frame.
+pub struct CollisionIndex {
+    pub hash: SpatialHash,
+    pub entities: Vec<(Entity, CollisionLayer)>,
+}
+
+impl Default for CollisionIndex {
+    fn default() -> Self {
+        Self {
+            hash: SpatialHash::new(COLLISION_CELL_SIZE),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl CollisionIndex {
+    /// Entities whose sprite bounds overlap the given box.
+    pub fn query(&self, center: Vec2, size: Vec2, mut f: impl FnMut(Entity, CollisionLayer)) {
+        self.hash.query(center, size, |index| {
+            let (entity, layer) = self.entities[index];
+            f(entity, layer)
+        });
+    }
+}
+
+/// Two entities overlap. `a` is always on the lower `CollisionLayer`,
+/// so a player laser hitting an enemy comes as `a: laser, b: enemy`.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+/// Projectiles used up on a hit this tick. One touching a few targets at once hits only
+/// the first system to take it, every system reacting to `CollisionEvent`s skips these.
+#[derive(Default)]
+pub struct SpentProjectiles(pub HashSet<Entity>);
+
+/// Half the size of the box around a `size` sprite, turned and scaled by its transform.
+fn turned_half_size(size: Vec2, tf: &Transform) -> Vec2 {
+    let half = size * tf.scale.xy().abs() / 2.;
+    let (x, y) = ((tf.rotation * Vec3::X).xy().abs(), (tf.rotation * Vec3::Y).xy().abs());
+    x * half.x + y * half.y
+}
+
+fn collision_index_system(
+    mut index: ResMut<CollisionIndex>,
+    query: Query<(Entity, &Transform, &SpriteSize, &CollisionLayer)>,
+) {
+    let index = &mut *index;
+    index.hash.clear();
+    index.entities.clear();
+    for (entity, tf, size, layer) in query.iter() {
+        // bounds of the turned sprite, lasers fly at an angle
+        index
+            .hash
+            .insert(tf.translation.xy(), turned_half_size(size.0, tf) * 2.);
+        index.entities.push((entity, *layer));
+    }
+}
+
+fn collision_detection_system(
+    index: Res<CollisionIndex>,
+    query: Query<(
+        &Transform,
+        &Hitbox,
+        Option<&AlphaMask>,
+        Option<&PixelPerfect>,
+    )>,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut spent: ResMut<SpentProjectiles>,
+) {
+    spent.0.clear();
+    index.hash.pairs(|i, j| {
+        let (mut a, mut a_layer) = index.entities[i];
+        let (mut b, mut b_layer) = index.entities[j];
+        if !a_layer.interacts(b_layer) {
+            return;
+        }
+        if b_layer < a_layer {
+            std::mem::swap(&mut a, &mut b);
+            std::mem::swap(&mut a_layer, &mut b_layer);
+        }
+        let (Ok((a_tf, a_hitbox, a_mask, a_pixel)), Ok((b_tf, b_hitbox, b_mask, b_pixel))) =
+            (query.get(a), query.get(b))
+        else {
+            return;
+        };
+        let hit = match (a_mask, b_mask) {
+            (Some(a_mask), Some(b_mask)) if a_pixel.is_some() || b_pixel.is_some() => {
+                a_mask.overlaps(a_tf, b_mask, b_tf)
+            }
+            _ => a_hitbox.overlaps(a_tf, b_hitbox, b_tf),
+        };
+        if hit {
+            collision_events.send(CollisionEvent { a, b });
+        }
+    });
+}
@@ -0,0 +1,209 @@
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// One piece of a hitbox, in sprite pixels around the entity's center.
+/// `Transform` applies to it: rects and capsules turn with the entity, scale applies
+/// (`scale.x` for radii).
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Shape {
+    Rect {
+        #[serde(default)]
+        offset: (f32, f32),
+        size: (f32, f32),
+    },
+    Circle {
+        #[serde(default)]
+        offset: (f32, f32),
+        radius: f32,
+    },
+    /// the segment `from`-`to`, thickened by `radius`
+    Capsule {
+        from: (f32, f32),
+        to: (f32, f32),
+        radius: f32,
+    },
+}
+
+/// What actually gets hit, one or more `Shape`s. Written in `.ron` files as a list:
+/// `[Circle(radius: 20.), Rect(offset: (0., -10.), size: (80., 12.))]`
+///
+/// The broad phase only looks at `SpriteSize`, so keep the shapes inside the sprite.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Hitbox {
+    pub shapes: Vec<Shape>,
+}
+
+impl Hitbox {
+    pub fn rect(size: (f32, f32)) -> Self {
+        Self {
+            shapes: vec![Shape::Rect {
+                offset: (0., 0.),
+                size,
+            }],
+        }
+    }
+
+    pub fn circle(radius: f32) -> Self {
+        Self {
+            shapes: vec![Shape::Circle {
+                offset: (0., 0.),
+                radius,
+            }],
+        }
+    }
+
+    /// The hitbox a data file gave, or the whole `size` sprite if it gave none.
+    pub fn or_rect(hitbox: &Option<Hitbox>, size: (f32, f32)) -> Self {
+        hitbox.clone().unwrap_or_else(|| Self::rect(size))
+    }
+
+    /// The upright capsule filling a `size` box, fits lasers and other long bullets.
+    pub fn capsule(size: (f32, f32)) -> Self {
+        let radius = size.0.min(size.1) / 2.;
+        let (x, y) = (size.0 / 2. - radius, size.1 / 2. - radius);
+        Self {
+            shapes: vec![Shape::Capsule {
+                from: (-x, -y),
+                to: (x, y),
+                radius,
+            }],
+        }
+    }
+
+    /// Whether the two hitboxes touch, each placed by its entity's transform.
+    pub fn overlaps(&self, tf: &Transform, other: &Hitbox, other_tf: &Transform) -> bool {
+        self.shapes.iter().any(|shape| {
+            let placed = Placed::new(shape, tf);
+            other
+                .shapes
+                .iter()
+                .any(|other| placed.overlaps(&Placed::new(other, other_tf)))
+        })
+    }
+}
+
+impl From<(f32, f32)> for Hitbox {
+    fn from(size: (f32, f32)) -> Self {
+        Hitbox::rect(size)
+    }
+}
+
+/// A shape in world space. Circles are capsules with both ends at the same point.
+enum Placed {
+    Rect {
+        center: Vec2,
+        axes: [Vec2; 2],
+        half: Vec2,
+    },
+    Capsule {
+        from: Vec2,
+        to: Vec2,
+        radius: f32,
+    },
+}
+
+impl Placed {
+    fn new(shape: &Shape, tf: &Transform) -> Self {
+        let point = |(x, y): (f32, f32)| tf.mul_vec3(Vec3::new(x, y, 0.)).xy();
+        let scale = tf.scale.xy();
+        match *shape {
+            Shape::Rect { offset, size } => Placed::Rect {
+                center: point(offset),
+                axes: [(tf.rotation * Vec3::X).xy(), (tf.rotation * Vec3::Y).xy()],
+                half: Vec2::from(size) * scale / 2.,
+            },
+            Shape::Circle { offset, radius } => Placed::Capsule {
+                from: point(offset),
+                to: point(offset),
+                radius: radius * scale.x,
+            },
+            Shape::Capsule { from, to, radius } => Placed::Capsule {
+                from: point(from),
+                to: point(to),
+                radius: radius * scale.x,
+            },
+        }
+    }
+
+    fn overlaps(&self, other: &Placed) -> bool {
+        match (self, other) {
+            (
+                Placed::Capsule { from, to, radius },
+                Placed::Capsule {
+                    from: other_from,
+                    to: other_to,
+                    radius: other_radius,
+                },
+            ) => segment_distance(*from, *to, *other_from, *other_to) <= radius + other_radius,
+            (Placed::Capsule { from, to, radius }, rect @ Placed::Rect { .. })
+            | (rect @ Placed::Rect { .. }, Placed::Capsule { from, to, radius }) => {
+                rect.distance_to_segment(*from, *to) <= *radius
+            }
+            (
+                Placed::Rect { center, axes, half },
+                Placed::Rect {
+                    center: other_center,
+                    axes: other_axes,
+                    half: other_half,
+                },
+            ) => {
+                // separating axis test, the candidate axes are the four box edges
+                let d = *other_center - *center;
+                axes.iter().chain(other_axes.iter()).all(|&axis| {
+                    let extent = |axes: &[Vec2; 2], half: &Vec2| {
+                        half.x * axes[0].dot(axis).abs() + half.y * axes[1].dot(axis).abs()
+                    };
+                    d.dot(axis).abs() <= extent(axes, half) + extent(other_axes, other_half)
+                })
+            }
+        }
+    }
+
+    /// 0 if the segment crosses the rect.
+    fn distance_to_segment(&self, from: Vec2, to: Vec2) -> f32 {
+        let (center, axes, half) = match self {
+            Placed::Rect { center, axes, half } => (*center, *axes, *half),
+            Placed::Capsule { .. } => unreachable!(),
+        };
+        let local = |p: Vec2| Vec2::new((p - center).dot(axes[0]), (p - center).dot(axes[1]));
+        let inside = |p: Vec2| p.x.abs() <= half.x && p.y.abs() <= half.y;
+        let (from, to) = (local(from), local(to));
+        if inside(from) || inside(to) {
+            return 0.;
+        }
+        let corners = [
+            Vec2::new(-half.x, -half.y),
+            Vec2::new(half.x, -half.y),
+            Vec2::new(half.x, half.y),
+            Vec2::new(-half.x, half.y),
+        ];
+        (0..4)
+            .map(|i| segment_distance(from, to, corners[i], corners[(i + 1) % 4]))
+            .fold(f32::MAX, f32::min)
+    }
+}
+
+fn point_segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0. {
+        ((p - a).dot(ab) / ab.length_squared()).clamp(0., 1.)
+    } else {
+        0.
+    };
+    p.distance(a + ab * t)
+}
+
+fn segment_distance(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> f32 {
+    let cross = |o: Vec2, p: Vec2, q: Vec2| (p - o).perp_dot(q - o);
+    let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+    let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+    if d1 * d2 < 0. && d3 * d4 < 0. {
+        return 0.;
+    }
+    point_segment_distance(a, c, d)
+        .min(point_segment_distance(b, c, d))
+        .min(point_segment_distance(c, a, b))
+        .min(point_segment_distance(d, a, b))
+}
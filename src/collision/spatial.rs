@@ -0,0 +1,106 @@
+use bevy::prelude::Vec2;
+use bevy::utils::StableHashMap;
+
+/// Uniform grid over axis aligned boxes. Rebuilt from scratch every frame:
+/// `clear`, `insert` everything, then ask for `pairs` or `query` an area.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: StableHashMap<(i32, i32), Vec<usize>>,
+    bounds: Vec<(Vec2, Vec2)>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: StableHashMap::default(),
+            bounds: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Keeps the cells used last time around, so a rebuild does not allocate.
+    pub fn clear(&mut self) {
+        self.cells.retain(|_, cell| !cell.is_empty());
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.bounds.clear();
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Adds a box given by its center and size, returns its index.
+    pub fn insert(&mut self, center: Vec2, size: Vec2) -> usize {
+        let index = self.bounds.len();
+        let (min, max) = (center - size / 2., center + size / 2.);
+        self.bounds.push((min, max));
+
+        let (min_cell, max_cell) = (self.cell(min), self.cell(max));
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+        index
+    }
+
+    /// Every pair of boxes that overlap, each pair once, lower index first.
+    pub fn pairs(&self, mut f: impl FnMut(usize, usize)) {
+        for (&cell, members) in self.cells.iter() {
+            for (i, &a) in members.iter().enumerate() {
+                for &b in &members[i + 1..] {
+                    let ((a_min, a_max), (b_min, b_max)) = (self.bounds[a], self.bounds[b]);
+                    if a_min.x > b_max.x
+                        || b_min.x > a_max.x
+                        || a_min.y > b_max.y
+                        || b_min.y > a_max.y
+                    {
+                        continue;
+                    }
+                    // boxes spanning several cells meet in several of them, only the cell
+                    // holding the corner of their overlap reports the pair
+                    if self.cell(a_min.max(b_min)) != cell {
+                        continue;
+                    }
+                    f(a.min(b), a.max(b));
+                }
+            }
+        }
+    }
+
+    /// Every box overlapping the given one, each once.
+    pub fn query(&self, center: Vec2, size: Vec2, mut f: impl FnMut(usize)) {
+        let (min, max) = (center - size / 2., center + size / 2.);
+        let (min_cell, max_cell) = (self.cell(min), self.cell(max));
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                let members = match self.cells.get(&(x, y)) {
+                    Some(members) => members,
+                    None => continue,
+                };
+                for &index in members {
+                    let (b_min, b_max) = self.bounds[index];
+                    if min.x > b_max.x || b_min.x > max.x || min.y > b_max.y || b_min.y > max.y {
+                        continue;
+                    }
+                    if self.cell(min.max(b_min)) == (x, y) {
+                        f(index);
+                    }
+                }
+            }
+        }
+    }
+}
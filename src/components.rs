@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::{prelude::{Component, Vec2, Vec3}, time::Timer};
 
 #[derive(Component)]
@@ -23,6 +25,75 @@ impl From<(f32, f32)> for SpriteSize {
 
 #[derive(Component)]
 pub struct Laser;
+/// How much health a projectile takes away when it hits.
+#[derive(Component)]
+pub struct Damage(pub u32);
+#[derive(Component)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+    /// returns true when this hit was the last one
+    pub fn take(&mut self, damage: u32) -> bool {
+        self.current = self.current.saturating_sub(damage);
+        self.current == 0
+    }
+}
+/// Tints the sprite for a moment after taking a hit that did not kill.
+#[derive(Component)]
+pub struct HitFlash(pub Timer);
+impl Default for HitFlash {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.08, false))
+    }
+}
+/// Shoots at most once per `cooldown`, the player's and the enemies' alike.
+#[derive(Component)]
+pub struct Weapon {
+    pub cooldown: Timer,
+    /// keeps firing while the trigger is held, otherwise one shot per press
+    pub auto_fire: bool,
+}
+impl Weapon {
+    /// `fire_rate` in shots per second. Ready to fire straight away.
+    pub fn new(fire_rate: f32, auto_fire: bool) -> Self {
+        let mut cooldown = Timer::from_seconds(1. / fire_rate, false);
+        let duration = cooldown.duration();
+        cooldown.tick(duration);
+        Self { cooldown, auto_fire }
+    }
+    /// Changes the rate, keeping how far the current cooldown has come.
+    pub fn set_fire_rate(&mut self, fire_rate: f32) {
+        let duration = Duration::from_secs_f32(1. / fire_rate);
+        if self.cooldown.duration() != duration {
+            self.cooldown.set_duration(duration);
+        }
+    }
+    /// Advances the cooldown and says whether a shot goes off this tick.
+    /// `held`: the trigger is down, `pressed`: it went down since the last tick.
+    pub fn trigger(&mut self, delta: Duration, held: bool, pressed: bool) -> bool {
+        // the part of the tick after the cooldown ran out counts towards the next one,
+        // so the fire rate is not rounded to whole ticks
+        let overshoot = if self.cooldown.finished() {
+            Duration::ZERO
+        } else {
+            delta.saturating_sub(self.cooldown.duration().saturating_sub(self.cooldown.elapsed()))
+        };
+        self.cooldown.tick(delta);
+        let pulled = if self.auto_fire { held || pressed } else { pressed };
+        if pulled && self.cooldown.finished() {
+            self.cooldown.reset();
+            self.cooldown.tick(overshoot);
+            true
+        } else {
+            false
+        }
+    }
+}
 #[derive(Component)]
 pub struct Background;
 //Player
@@ -30,11 +101,19 @@ pub struct Background;
 pub struct Player;
 #[derive(Component)]
 pub struct FromPlayer;
-//Enemy
+/// Enemy lasers pass through the player while this runs, the sprite blinks meanwhile.
 #[derive(Component)]
-pub struct Enemy;
+pub struct Invulnerable(pub Timer);
+impl Invulnerable {
+    pub fn from_seconds(duration: f32) -> Self {
+        Self(Timer::from_seconds(duration, false))
+    }
+}
+//Enemy
 #[derive(Component)]
-pub struct EnemyT2;
+pub struct Enemy {
+    pub archetype: usize, //index into EnemyArchetypes
+}
 #[derive(Component)]
 pub struct FromEnemy;
 
@@ -44,10 +123,35 @@ pub struct FromEnemy;
 pub struct Explosion;
 #[derive(Component)]
 pub struct ExplosionToSpawn(pub Vec3);
+/// Put next to `ExplosionToSpawn` for a bigger explosion than the usual one.
+#[derive(Component)]
+pub struct ExplosionScale(pub f32);
 #[derive(Component)]
 pub struct ExplosionTimer(pub Timer);
 impl Default for ExplosionTimer {
     fn default() -> Self {
         Self(Timer::from_seconds(0.05, true))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shots fired holding the trigger for `seconds` of 60 Hz ticks.
+    fn shots(fire_rate: f32, seconds: u32) -> u32 {
+        let mut weapon = Weapon::new(fire_rate, true);
+        let tick = Duration::from_secs_f64(1. / 60.);
+        (0..seconds * 60)
+            .filter(|_| weapon.trigger(tick, true, false))
+            .count() as u32
+    }
+
+    #[test]
+    fn fire_rate_is_not_rounded_to_ticks() {
+        // 8 and 9 shots a second fall between whole ticks
+        assert_eq!(shots(8., 10), 80);
+        assert_eq!(shots(9., 10), 90);
+        assert_eq!(shots(0.5, 10), 5);
+    }
+}
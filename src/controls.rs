@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::data::{asset_path, load_ron, DataError};
+use crate::CONTROLS_FILE;
+
+/// Turns keys and gamepad buttons into `Action`s, as bound in `controls.ron`.
+/// The game only ever looks at `ActionState`, never at the devices.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        let controls =
+            Controls::load().unwrap_or_else(|err| panic!("could not load controls: {}", err));
+        app.insert_resource(controls)
+            .insert_resource(ActionState::default())
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                action_system.label(ActionSystem).after(InputSystem),
+            );
+    }
+}
+
+/// Systems overriding `ActionState` (replay playback) run `.after(ActionSystem)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct ActionSystem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Bomb,
+    Pause,
+    /// move slower, for squeezing between bullets
+    Focus,
+    /// out to the main menu, from pause and game over
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Bomb,
+        Action::Pause,
+        Action::Focus,
+        Action::Back,
+    ];
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// Which keys and buttons trigger which action. An action can have any number of both.
+#[derive(Debug, Deserialize)]
+pub struct Controls {
+    #[serde(default)]
+    keys: HashMap<Action, Vec<KeyCode>>,
+    #[serde(default)]
+    buttons: HashMap<Action, Vec<GamepadButtonType>>,
+    /// stick deflection below this counts as centered, 0 to 1
+    deadzone: f32,
+}
+
+impl Controls {
+    /// The player's own `controls.ron` from the config directory if there is one,
+    /// the one shipped in `assets/` otherwise.
+    pub fn load() -> Result<Self, DataError> {
+        let path = dirs::config_dir()
+            .map(|dir| dir.join("bevy_oyun").join(CONTROLS_FILE))
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| asset_path(CONTROLS_FILE));
+        let controls: Controls = load_ron(&path)?;
+        if !(0. ..1.).contains(&controls.deadzone) {
+            return Err(DataError::Format(
+                path,
+                format!("deadzone {} is not between 0 and 1", controls.deadzone),
+            ));
+        }
+        Ok(controls)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The first key bound to `action`, for the hints on screen.
+    pub fn key_name(&self, action: Action) -> String {
+        match self.keys(action).first() {
+            Some(key) => format!("{:?}", key),
+            None => format!("{:?}", action),
+        }
+    }
+
+    /// Rescales the stick so movement starts from 0 at the edge of the deadzone.
+    fn apply_deadzone(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length();
+        if length <= self.deadzone {
+            return Vec2::ZERO;
+        }
+        let scaled = ((length - self.deadzone) / (1. - self.deadzone)).min(1.);
+        stick / length * scaled
+    }
+}
+
+/// The actions held down this frame, and where the player wants to move.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActionState {
+    pressed: u16,
+    just_pressed: u16,
+    /// each axis -1 to 1, from the move actions or the left stick
+    pub movement: Vec2,
+}
+
+impl ActionState {
+    /// `pressed` and `just_pressed` hold one bit per action, in `Action::ALL` order.
+    pub fn from_bits(pressed: u16, just_pressed: u16, movement: Vec2) -> Self {
+        Self {
+            pressed,
+            just_pressed,
+            movement,
+        }
+    }
+
+    pub fn bits(&self) -> (u16, u16) {
+        (self.pressed, self.just_pressed)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed & action.bit() != 0
+    }
+
+    /// The press is handled, nothing else reacts to it this frame
+    /// (the state it switched to, for one).
+    pub fn consume(&mut self, action: Action) {
+        self.just_pressed &= !action.bit();
+    }
+}
+
+fn action_system(
+    controls: Res<Controls>,
+    kb: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<ActionState>,
+) {
+    let mut pressed = 0;
+    let mut just_pressed = 0;
+    for action in Action::ALL {
+        let keys = controls.keys(action).iter().copied();
+        let buttons = || {
+            controls.buttons(action).iter().flat_map(|&button_type| {
+                gamepads
+                    .iter()
+                    .map(move |&gamepad| GamepadButton::new(gamepad, button_type))
+            })
+        };
+        if kb.any_pressed(keys.clone()) || gamepad_buttons.any_pressed(buttons()) {
+            pressed |= action.bit();
+        }
+        if kb.any_just_pressed(keys) || gamepad_buttons.any_just_pressed(buttons()) {
+            just_pressed |= action.bit();
+        }
+    }
+    let mut state = ActionState::from_bits(pressed, just_pressed, Vec2::ZERO);
+
+    let digital = |positive: Action, negative: Action| {
+        state.pressed(positive) as i8 as f32 - state.pressed(negative) as i8 as f32
+    };
+    let movement = Vec2::new(
+        digital(Action::MoveRight, Action::MoveLeft),
+        digital(Action::MoveUp, Action::MoveDown),
+    );
+    // the d-pad and keys win over the stick
+    state.movement = if movement != Vec2::ZERO {
+        movement
+    } else {
+        gamepads
+            .iter()
+            .map(|&gamepad| {
+                let axis = |axis_type| {
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.)
+                };
+                controls.apply_deadzone(Vec2::new(
+                    axis(GamepadAxisType::LeftStickX),
+                    axis(GamepadAxisType::LeftStickY),
+                ))
+            })
+            .find(|stick| *stick != Vec2::ZERO)
+            .unwrap_or(Vec2::ZERO)
+    };
+    *actions = state;
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Where bevy's `AssetServer` looks for assets, usable without one (headless, tools).
+pub fn asset_path(path: impl AsRef<Path>) -> PathBuf {
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|_| {
+            std::env::current_exe()
+                .map(|exe| exe.parent().map(Path::to_path_buf).unwrap_or_default())
+        })
+        .unwrap_or_default();
+    root.join("assets").join(path)
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(PathBuf, io::Error),
+    Ron(PathBuf, ron::Error),
+    Image(PathBuf, String),
+    /// read fine, but not what it should be
+    Format(PathBuf, String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            DataError::Ron(path, err) => write!(f, "{}: {}", path.display(), err),
+            DataError::Image(path, err) => write!(f, "{}: {}", path.display(), err),
+            DataError::Format(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+pub fn load_ron<T: DeserializeOwned>(path: &Path) -> Result<T, DataError> {
+    let text = fs::read_to_string(path).map_err(|err| DataError::Io(path.to_path_buf(), err))?;
+    ron::from_str(&text).map_err(|err| DataError::Ron(path.to_path_buf(), err))
+}
+
+/// Writes `value` so `load_ron` reads it back, after the `header` lines (comments).
+/// Nested values stay on one line each, the way the hand-written files look.
+pub fn save_ron<T: Serialize>(path: &Path, value: &T, header: &str) -> Result<(), DataError> {
+    let config = ron::ser::PrettyConfig::new().depth_limit(1);
+    let text = ron::ser::to_string_pretty(value, config)
+        .map_err(|err| DataError::Ron(path.to_path_buf(), err))?;
+    fs::write(path, format!("{}{}\n", header, text))
+        .map_err(|err| DataError::Io(path.to_path_buf(), err))
+}
+
+/// Loads every `.ron` file in `dir`, sorted by file name so the order never changes.
+/// Each value comes with its file stem, which is the name the game refers to it by.
+///
+/// `check` sees every value as it is read and can fill in what the file leaves out,
+/// its errors come back as a `DataError::Format` of that file.
+pub fn load_ron_dir<T: DeserializeOwned>(
+    dir: &Path,
+    mut check: impl FnMut(&mut T) -> Result<(), String>,
+) -> Result<Vec<(String, T)>, DataError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| DataError::Io(dir.to_path_buf(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut value = load_ron(path)?;
+            check(&mut value).map_err(|err| DataError::Format(path.clone(), err))?;
+            Ok((name, value))
+        })
+        .collect()
+}
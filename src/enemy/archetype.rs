@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::{AlphaMask, Hitbox};
+use crate::data::{asset_path, load_ron_dir, DataError};
+use crate::pattern::Patterns;
+use crate::pickup::Drop;
+
+use super::formation::FormationKind;
+use super::path::PathFormations;
+
+/// One kind of enemy, read from a `.ron` file in `assets/enemies/`.
+/// The file name (without extension) is the archetype's name.
+#[derive(Clone, Deserialize)]
+pub struct EnemyArchetype {
+    #[serde(skip)]
+    pub name: String,
+    pub sprite: String,
+    /// sprite size in pixels
+    pub size: (f32, f32),
+    /// see `Hitbox::or_rect`
+    #[serde(default)]
+    pub hitbox: Option<Hitbox>,
+    pub health: u32,
+    pub speed: f32,
+    pub formation: FormationKind,
+    pub weapon: Option<EnemyWeapon>,
+    pub score: u32,
+    /// rolled in order when destroyed, the first one that comes up drops
+    #[serde(default)]
+    pub drops: Vec<Drop>,
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+    #[serde(skip)]
+    pub mask: Option<AlphaMask>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct EnemyWeapon {
+    pub sprite: String,
+    pub size: (f32, f32),
+    /// an upright capsule filling `size` if not given
+    #[serde(default)]
+    pub hitbox: Option<Hitbox>,
+    /// in the same unit as `Velocity`, which way is up to the pattern
+    pub speed: f32,
+    pub damage: u32,
+    /// volleys per second
+    pub fire_rate: f32,
+    /// name of a pattern in `assets/patterns.ron`
+    pub pattern: String,
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+    #[serde(skip)]
+    pub mask: Option<AlphaMask>,
+}
+
+impl EnemyArchetype {
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox::or_rect(&self.hitbox, self.size)
+    }
+}
+
+impl EnemyWeapon {
+    pub fn hitbox(&self) -> Hitbox {
+        self.hitbox
+            .clone()
+            .unwrap_or_else(|| Hitbox::capsule(self.size))
+    }
+
+    pub fn validate(&self, patterns: &Patterns) -> Result<(), String> {
+        if self.fire_rate <= 0. {
+            return Err(format!("fire_rate {} has to be above 0", self.fire_rate));
+        }
+        if !patterns.contains(&self.pattern) {
+            return Err(format!("unknown pattern \"{}\"", self.pattern));
+        }
+        Ok(())
+    }
+}
+
+/// All enemy archetypes, indexed by `Enemy::archetype`.
+pub struct EnemyArchetypes(pub Vec<EnemyArchetype>);
+
+impl EnemyArchetypes {
+    pub fn load(
+        dir: &str,
+        patterns: &Patterns,
+        paths: &PathFormations,
+    ) -> Result<Self, DataError> {
+        let archetypes = load_ron_dir(&asset_path(dir), |archetype: &mut EnemyArchetype| {
+            archetype.formation.check(paths)?;
+            archetype.mask = AlphaMask::load_or_warn(&archetype.sprite);
+            if let Some(weapon) = &mut archetype.weapon {
+                weapon.validate(patterns)?;
+                weapon.mask = AlphaMask::load_or_warn(&weapon.sprite);
+            }
+            Ok(())
+        })?
+        .into_iter()
+        .map(|(name, archetype)| EnemyArchetype { name, ..archetype })
+        .collect();
+        Ok(Self(archetypes))
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|archetype| archetype.name == name)
+    }
+
+    /// Fills in the texture handles, the files only name the sprites.
+    pub fn load_textures(&mut self, asset_server: &AssetServer) {
+        for archetype in self.0.iter_mut() {
+            archetype.texture = asset_server.load(&archetype.sprite);
+            if let Some(weapon) = &mut archetype.weapon {
+                weapon.texture = asset_server.load(&weapon.sprite);
+            }
+        }
+    }
+}
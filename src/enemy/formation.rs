@@ -1,10 +1,17 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::Component;
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{WinSize, TIME_STEP};
 
-use crate::{WinSize, FORMATION_MEMBERS_MAX, BASE_SPEED};
+use super::orbit::{OrbitFormation, OrbitFormations};
+use super::path::{PathFollower, PathFormations};
 
 
 
+/// Flies in from `start` and circles `pivot` on an ellipse of `radius`.
 #[derive(Clone,Component)]
 pub struct Formation{
     pub start:(f32,f32),
@@ -14,82 +21,102 @@ pub struct Formation{
     pub angle : f32,
 }
 
+impl Formation {
+    /// Where an enemy at `(x_org,y_org)` is one tick later. It heads for its spot on the
+    /// ellipse, which only moves on once the enemy has caught up.
+    pub fn step(&mut self,(x_org,y_org) : (f32,f32)) -> (f32,f32){
+        let max_distance = TIME_STEP * self.speed;
+
+        let dir :f32 = if self.start.0 < 0. {1.} else {-1.};
+        let (x_pivot,y_pivot) = self.pivot;
+        let (x_radius,y_radius) = self.radius;
+
+        let angle = self.angle + dir * self.speed * TIME_STEP / (x_radius.min(y_radius) * PI / 2.);
+
+        let x_dst = x_radius * angle.cos() + x_pivot;
+        let y_dst = y_radius * angle.sin() + y_pivot;
+
+        let dx = x_org - x_dst;
+        let dy = y_org - y_dst;
+        let distance = (dx * dx + dy * dy).sqrt();
+        let distance_ratio = if distance != 0. {max_distance / distance} else {0.};
+
+        //son x ve y yi hesapla
+        let x = x_org - dx * distance_ratio;
+        let x = if dx > 0. {x.max(x_dst)} else {x.min(x_dst)};
+        let y = y_org - dy * distance_ratio;
+        let y = if dy > 0. {y.max(y_dst)} else {y.min(y_dst)};
+
+        if distance < max_distance * self.speed /20. {
+            self.angle = angle;
+        }
+        (x,y)
+    }
+}
+
+#[derive(Clone,Debug,Deserialize)]
+pub enum FormationKind{
+    Elips,
+    PointTop,
+    /// a path from `assets/formations/`, by name
+    Path(String),
+}
+
+impl FormationKind {
+    pub fn check(&self, paths : &PathFormations) -> Result<(), String>{
+        match self {
+            FormationKind::Path(name) if paths.get(name).is_none() => Err(format!("unknown formation \"{}\"", name)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How a new enemy moves, around a pivot or along a path.
+pub enum EnemyMovement{
+    Formation(Formation),
+    Path(PathFollower),
+}
+
+/// Enemies of a circling formation come in groups of `OrbitFormation::members`
+/// sharing one template, one group per kind at a time.
 #[derive(Default)]
 pub struct FormationMaker{
-    current_template : Option<Formation>,
-    current_members : u32,
+    elips : Template,
+    point_top : Template,
 }
-impl FormationMaker {
 
-    pub fn make_point_top(&mut self,win_size : &WinSize) -> Formation{
-        let mut rng = thread_rng();
-        let w_span = win_size.w;
-        let h_span = win_size.h / 2. + 200.;
-
-        let x = if rng.gen_bool(0.5) {w_span} else {-w_span};
-        let y = rng.gen_range(160.0..h_span);
-        let start = (x,y);
-        let(radius,angle,speed) = ((10.,10.),0.,BASE_SPEED);
-
-        let w_span = win_size.w / 2.;
-        let h_span = win_size.h / 2. - 80.;
-        let pivot = (rng.gen_range(-w_span..w_span),rng.gen_range(50.0..h_span));
-        let formation = Formation{
-            angle,
-            start,
-            pivot,
-            speed,
-            radius,
-        };
-        formation
-    }
-    pub fn make_elips(&mut self,win_size : &WinSize) -> Formation{
-        match (&self.current_template , self.current_members >= FORMATION_MEMBERS_MAX) {
-            (Some(tmpl),false) => {
-                self.current_members +=1;
-                tmpl.clone()
+#[derive(Default)]
+struct Template{
+    formation : Option<Formation>,
+    members : u32,
+}
+
+impl Template {
+    fn make(&mut self,orbit : &OrbitFormation,win_size : &WinSize,rng : &mut impl Rng) -> Formation{
+        match &self.formation {
+            Some(formation) if self.members < orbit.members => {
+                self.members += 1;
+                formation.clone()
             }
-            (None,_) | (_,true) => {
-                let mut rng = thread_rng();
-
-                //  başlangıç x ve y sini hesapla
-                let w_span = win_size.w / 2. +100.;
-                let h_span = win_size.h / 2. +100.;
-                
-                let x = if rng.gen_bool(0.5) {w_span} else {-w_span};
-                let y = rng.gen_range(0.0..h_span) as f32;
-                let start = (x,y);
-
-                //ekseni hesapla
-                let w_span = win_size.w / 4.;
-                let h_span = win_size.h / 3. - 50.;
-                let pivot = (rng.gen_range(-w_span..w_span),rng.gen_range(0.0..h_span));
-
-                //açıyı yarıçap
-                let radius = (rng.gen_range(80.0..150.0),100.);
-
-                //açıyı hesapla
-                let angle = (y - pivot.1).atan2(x - pivot.0);
-
-                //hız
-                let speed = BASE_SPEED; 
-                
-                //düzeni oluştur
-                let formation = Formation{
-                    angle,
-                    pivot,
-                    radius,
-                    speed,
-                    start,
-                };
-                // şablon olarak kaydet
-                self.current_template = Some(formation.clone());
-                // 1 üye olarak sıfırla 
-                self.current_members = 1;
-
-                formation    
+            _ => {
+                let formation = orbit.make(win_size,rng);
+                // şablon olarak kaydet, 1 üye olarak sıfırla
+                self.formation = Some(formation.clone());
+                self.members = 1;
+                formation
             }
-            
         }
     }
-}
\ No newline at end of file
+}
+
+impl FormationMaker {
+
+    pub fn make(&mut self,kind : &FormationKind,win_size : &WinSize,rng : &mut impl Rng,orbits : &OrbitFormations,paths : &PathFormations) -> EnemyMovement{
+        match kind {
+            FormationKind::Elips => EnemyMovement::Formation(self.elips.make(&orbits.elips,win_size,rng)),
+            FormationKind::PointTop => EnemyMovement::Formation(self.point_top.make(&orbits.point_top,win_size,rng)),
+            // checked when loaded
+            FormationKind::Path(name) => EnemyMovement::Path(paths.get(name).unwrap().follower(win_size)),
+        }
+    }
+}
@@ -1,179 +1,161 @@
-use std::f32::consts::PI;
+use std::f32::consts::FRAC_PI_2;
 
-use crate::{GameTextures, SPRITE_SCALE, WinSize, components::{Enemy, SpriteSize, Laser, Movable, FromEnemy, Velocity, EnemyT2}, ENEMY_SIZE, EnemyCount, ENEMY_MAX, ENEMY_LASER_SIZE, TIME_STEP, ENEMYT2_MAX, ENEMYT2_SIZE};
-use bevy::{prelude::*, time::FixedTimestep, ecs::{query, schedule::ShouldRun}, transform};
-use rand::{thread_rng, Rng};
+use crate::{SPRITE_SCALE, WinSize, components::{Enemy, Player, SpriteSize, Laser, Movable, FromEnemy, Velocity, Damage, Health}, ENEMY_ARCHETYPES_DIR, FORMATIONS_DIR, ORBITS_FILE};
+use bevy::prelude::*;
 
-use self::formation::{FormationMaker, Formation};
+use crate::collision::CollisionLayer;
+use crate::pattern::{fire_pattern, BulletQueue, BulletQueueSystem, Gun, Patterns, WeaponRef};
+use crate::simulation::{Interpolated, SimTime, SimulationStage};
+use crate::state::{despawn_with, AppState, GameStep, InGameStep};
 
-mod formation;
+use self::archetype::{EnemyArchetype, EnemyArchetypes, EnemyWeapon};
+use self::formation::{EnemyMovement, FormationMaker, Formation};
+use self::orbit::OrbitFormations;
+use self::path::{PathEnd, PathFollower, PathFormations};
+
+pub mod archetype;
+pub mod formation;
+pub mod orbit;
+pub mod path;
 
 
 pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
+        let paths = PathFormations::load(FORMATIONS_DIR)
+            .unwrap_or_else(|err| panic!("could not load formations: {}", err));
+        let orbits = OrbitFormations::load(ORBITS_FILE)
+            .unwrap_or_else(|err| panic!("could not load orbit formations: {}", err));
+        let archetypes = EnemyArchetypes::load(ENEMY_ARCHETYPES_DIR, app.world.resource::<Patterns>(), &paths)
+            .unwrap_or_else(|err| panic!("could not load enemy archetypes: {}", err));
         app
+        .insert_resource(paths)
+        .insert_resource(orbits)
+        .insert_resource(archetypes)
         .insert_resource(FormationMaker::default())
-        .add_system_set(SystemSet::new()
-            .with_run_criteria(FixedTimestep::step(1.))
-            .with_system(enemy_spawn_system))
-        .add_system_set(SystemSet::new()
-            .with_run_criteria(FixedTimestep::step(5.))
-            .with_system(enemyt2_spawn_system))
-        .add_system_set(SystemSet::new()
-        .with_run_criteria(enemy_fire_criteria)
-        .with_system(enemy_fire_system))
-        .add_system(enemy_movement_system)
-        .add_system(enemy_t2_movement_system);
+        .add_system_set(SystemSet::on_enter(AppState::Playing)
+            .with_system(|mut commands: Commands| commands.insert_resource(FormationMaker::default())))
+        .add_system_set_to_stage(SimulationStage, SystemSet::new()
+            .with_system(enemy_fire_system.in_step(GameStep::EnemyFire).after(BulletQueueSystem))
+            .with_system(enemy_movement_system.in_step(GameStep::Movement))
+            .with_system(enemy_path_system.in_step(GameStep::Movement).after(enemy_movement_system)))
+        .add_system_set(SystemSet::on_exit(AppState::Playing)
+            .with_system(despawn_with::<Enemy>));
 
     }
 }
 
-fn enemy_movement_system(time : Res<Time>,mut query : Query<(&mut Transform, &mut Formation),With<Enemy>>){
+fn enemy_movement_system(mut query : Query<(&mut Transform, &mut Formation),With<Enemy>>){
     for (mut transform , mut formation) in query.iter_mut(){
-        let (x_org,y_org) = (transform.translation.x,transform.translation.y);
-
-        let max_distance = TIME_STEP * formation.speed;
-
-        let dir :f32 = if formation.start.0 < 0. {1.} else {-1.};
-        let (x_pivot,y_pivot) = formation.pivot;
-        let (x_radius,y_radius) = formation.radius;
-
-        let angle = formation.angle + dir * formation.speed * TIME_STEP / (x_radius.min(y_radius) * PI / 2.);
-
-        let x_dst = x_radius * angle.cos() + x_pivot;
-        let y_dst = y_radius * angle.sin() + y_pivot;
-
-        let dx = x_org - x_dst;
-        let dy = y_org - y_dst;
-        let distance = (dx * dx + dy * dy).sqrt();
-        let distance_ratio = if distance != 0. {max_distance / distance} else {0.};
-
-        //son x ve y yi hesapla
-        let x = x_org - dx * distance_ratio;
-        let x = if dx > 0. {x.max(x_dst)} else {x.min(x_dst)};
-        let y = y_org - dy * distance_ratio;
-        let y = if dy > 0. {y.max(y_dst)} else {y.min(y_dst)};
-
-        if distance < max_distance * formation.speed /20. {
-            formation.angle = angle;
-        }
-
         let translation = &mut transform.translation;
-        (translation.x,translation.y) = (x,y);
-    }
-}
-fn enemy_fire_criteria () -> ShouldRun {
-    if thread_rng().gen_bool(1./60.){
-        ShouldRun::Yes
-    }else {
-        ShouldRun::No
+        (translation.x,translation.y) = formation.step((translation.x,translation.y));
     }
 }
 
-fn enemy_fire_system(
+fn enemy_path_system(
     mut commands : Commands,
-    game_textures : Res<GameTextures>,
-    enemy_query : Query<&Transform,With<Enemy>>
+    sim_time : Res<SimTime>,
+    win_size : Res<WinSize>,
+    mut query : Query<(Entity, &mut Transform, &mut PathFollower, Option<&mut Interpolated>),With<Enemy>>
 ){
-    for &tf in enemy_query.iter(){
-        let (x,y)= (tf.translation.x,tf.translation.y);
-        commands.spawn_bundle(SpriteBundle {
-            texture : game_textures.enemy_laser.clone(),
-            transform : Transform{
-                translation : Vec3::new(x,y-15.,6.),
-                scale : Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Laser)
-        .insert(SpriteSize::from(ENEMY_LASER_SIZE))
-        .insert(FromEnemy)
-        .insert(Movable{auto_despawn : true})
-        .insert(Velocity{x:0. , y:-1.2});
+    for (entity, mut transform, mut path, interpolated) in query.iter_mut(){
+        let wrapped = path.advance(sim_time.delta_seconds());
+        let (position, _) = path.at(path.distance);
+        transform.translation = position.extend(transform.translation.z);
+        if let (true, Some(mut interpolated)) = (wrapped, interpolated) {
+            interpolated.teleport(transform.translation);
+        }
+        // gone once the path ran out and the screen too
+        const MARGIN: f32 = 100.;
+        if path.end() == PathEnd::Exit && path.finished()
+            && (position.x.abs() > win_size.w / 2. + MARGIN || position.y.abs() > win_size.h / 2. + MARGIN) {
+            commands.entity(entity).despawn();
+        }
     }
 }
 
-fn enemy_spawn_system(
+fn enemy_fire_system(
     mut commands : Commands,
-    mut enemy_count : ResMut<EnemyCount>,
-    mut formation_maker : ResMut<FormationMaker>,
-    game_textures : Res<GameTextures>,
-    win_size : Res<WinSize  >)
-{
-    if enemy_count.0 < ENEMY_MAX{
-        // hareket düzenini getir
-        let formation = formation_maker.make_elips(&win_size);
-        let (x,y) = formation.start;
-
-        commands.spawn_bundle(SpriteBundle {
-            texture: game_textures.enemy.clone(),
-            transform: Transform{
-                translation: Vec3 { x: (x), y: (y), z: (10.) },
-                scale: Vec3::new(SPRITE_SCALE,SPRITE_SCALE,1.),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Enemy)
-        .insert(SpriteSize::from(ENEMY_SIZE))
-        .insert(formation);
-        enemy_count.0 +=1;
+    archetypes : Res<EnemyArchetypes>,
+    patterns : Res<Patterns>,
+    sim_time : Res<SimTime>,
+    player_query : Query<&Transform, With<Player>>,
+    mut enemy_query : Query<(&Transform, &Enemy, &mut Gun, &mut BulletQueue)>
+){
+    let player = player_query.get_single().ok().map(|tf| tf.translation.truncate());
+    for (tf, enemy, mut gun, mut queue) in enemy_query.iter_mut(){
+        let weapon = match &archetypes.0[enemy.archetype].weapon {
+            Some(weapon) => weapon,
+            None => continue,
+        };
+        if let Some(volley) = gun.trigger(sim_time.delta()) {
+            fire_pattern(&mut commands, &patterns, weapon, WeaponRef::Enemy(enemy.archetype), volley, tf, Vec2::new(0.,-15.), player, &mut queue);
+        }
     }
 }
-fn enemyt2_spawn_system(mut commands : Commands,mut enemy_count: ResMut<EnemyCount>,mut formation_maker : ResMut<FormationMaker>,game_textures : Res<GameTextures>,win_size : Res<WinSize>){
-    if enemy_count.1 < ENEMYT2_MAX {
-        let formation = formation_maker.make_point_top(&win_size);
-        let (x,y) = formation.start;
-        commands.spawn_bundle(SpriteBundle {
-            texture : game_textures.enemy_t2.clone(),
-            transform: Transform {
-                translation : Vec3::new(x,y,10.),
-                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 10.),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(EnemyT2)
-        .insert(SpriteSize::from(ENEMYT2_SIZE))
-        .insert(formation);
-        println!("spawned");
-        enemy_count.1 += 1;
+
+/// Enemy and boss fire alike, the sprite turned to fly along `velocity`.
+pub fn spawn_enemy_laser(commands : &mut Commands, weapon : &EnemyWeapon, position : Vec3, velocity : Velocity) -> Entity{
+    // the sprites point down
+    let rotation = Quat::from_rotation_z(velocity.y.atan2(velocity.x) + FRAC_PI_2);
+    let mut laser = commands.spawn_bundle(SpriteBundle {
+        texture : weapon.texture.clone(),
+        transform : Transform{
+            translation : position,
+            rotation,
+            scale : Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
+        },
+        ..Default::default()
+    });
+    laser.insert(Laser)
+    .insert(SpriteSize::from(weapon.size))
+    .insert(weapon.hitbox())
+    .insert(CollisionLayer::EnemyProjectile)
+    .insert(FromEnemy)
+    .insert(Damage(weapon.damage))
+    .insert(Movable{auto_despawn : true})
+    .insert(velocity);
+    if let Some(mask) = &weapon.mask {
+        laser.insert(mask.clone());
     }
+    laser.id()
 }
 
-fn enemy_t2_movement_system(time : Res<Time>,mut query : Query<(&mut Transform, &mut Formation),With<EnemyT2>>){
-    for (mut transform , mut formation) in query.iter_mut(){
-        let (x_org,y_org) = (transform.translation.x,transform.translation.y);
-
-        let max_distance = TIME_STEP * formation.speed;
-
-        let dir :f32 = if formation.start.0 < 0. {1.} else {-1.};
-        let (x_pivot,y_pivot) = formation.pivot;
-        let (x_radius,y_radius) = formation.radius;
-
-        let angle = formation.angle + dir * formation.speed * TIME_STEP / (x_radius.min(y_radius) * PI / 2.);
-
-        let x_dst = x_radius * angle.cos() + x_pivot;
-        let y_dst = y_radius * angle.sin() + y_pivot;
-
-        let dx = x_org - x_dst;
-        let dy = y_org - y_dst;
-        let distance = (dx * dx + dy * dy).sqrt();
-        let distance_ratio = if distance != 0. {max_distance / distance} else {0.};
-
-        //son x ve y yi hesapla
-        let x = x_org - dx * distance_ratio;
-        let x = if dx > 0. {x.max(x_dst)} else {x.min(x_dst)};
-        let y = y_org - dy * distance_ratio;
-        let y = if dy > 0. {y.max(y_dst)} else {y.min(y_dst)};
-
-        if distance < max_distance * formation.speed /20. {
-            formation.angle = angle;
+/// The one way enemies enter the game, whatever their archetype.
+pub fn spawn_enemy(commands : &mut Commands, index : usize, archetype : &EnemyArchetype, movement : EnemyMovement) -> Entity{
+    let (x,y) = match &movement {
+        EnemyMovement::Formation(formation) => formation.start,
+        EnemyMovement::Path(path) => path.start().into(),
+    };
+    let mut enemy = commands.spawn_bundle(SpriteBundle {
+        texture: archetype.texture.clone(),
+        transform: Transform{
+            translation: Vec3::new(x,y,10.),
+            scale: Vec3::new(SPRITE_SCALE,SPRITE_SCALE,1.),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    enemy.insert(Enemy{archetype : index})
+    .insert(SpriteSize::from(archetype.size))
+    .insert(archetype.hitbox())
+    .insert(CollisionLayer::Enemy)
+    .insert(Health::new(archetype.health));
+    match movement {
+        EnemyMovement::Formation(formation) => {
+            enemy.insert(Formation{speed : archetype.speed, ..formation});
         }
-
-        let translation = &mut transform.translation;
-        (translation.x,translation.y) = (x,y);
+        // paths have their own speed
+        EnemyMovement::Path(path) => {
+            enemy.insert(path);
+        }
+    }
+    if let Some(mask) = &archetype.mask {
+        enemy.insert(mask.clone());
     }
-}
\ No newline at end of file
+    if let Some(weapon) = &archetype.weapon {
+        enemy.insert(Gun::new(weapon))
+        .insert(BulletQueue::default());
+    }
+    enemy.id()
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{asset_path, load_ron, DataError};
+use crate::{WinSize, BASE_SPEED, WINDOW_SIZE};
+
+use super::formation::Formation;
+
+/// Where enemies circling a pivot come from and what they circle, for `FormationKind::Elips`
+/// and `FormationKind::PointTop`. Each range is `(from, to)`, picked from at random.
+///
+/// Like paths, in pixels of the default window around its center, stretched with the window.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrbitFormation {
+    /// how far left or right of the center they come in, the side is picked at random
+    pub start_x: f32,
+    pub start_y: (f32, f32),
+    pub pivot_x: (f32, f32),
+    pub pivot_y: (f32, f32),
+    pub radius_x: (f32, f32),
+    pub radius_y: (f32, f32),
+    /// radians around the pivot to start circling at, towards the start if not given
+    #[serde(default)]
+    pub angle: Option<f32>,
+    /// enemies in a row sharing one start and pivot, before new ones are picked
+    pub members: u32,
+}
+
+impl OrbitFormation {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.members == 0 {
+            return Err("members has to be at least 1".to_string());
+        }
+        let radii = [self.radius_x, self.radius_y];
+        if radii.iter().any(|&(from, to)| from <= 0. || to <= 0.) {
+            return Err("radius_x and radius_y have to be above 0".to_string());
+        }
+        Ok(())
+    }
+
+    /// A new start, pivot and radius for a window of `win_size`. The speed is the enemy's.
+    pub fn make(&self, win_size: &WinSize, rng: &mut impl Rng) -> Formation {
+        let stretch = Vec2::new(win_size.w / WINDOW_SIZE.0, win_size.h / WINDOW_SIZE.1);
+        let side = if rng.gen_bool(0.5) { 1. } else { -1. };
+        let start = Vec2::new(side * self.start_x, pick(rng, self.start_y)) * stretch;
+        let pivot = Vec2::new(pick(rng, self.pivot_x), pick(rng, self.pivot_y)) * stretch;
+        let radius = Vec2::new(pick(rng, self.radius_x), pick(rng, self.radius_y)) * stretch;
+        let angle = self
+            .angle
+            .unwrap_or_else(|| (start.y - pivot.y).atan2(start.x - pivot.x));
+        Formation {
+            start: start.into(),
+            radius: radius.into(),
+            pivot: pivot.into(),
+            speed: BASE_SPEED,
+            angle,
+        }
+    }
+}
+
+/// Anywhere in the range, in either order. No random number is used up on a fixed value.
+fn pick(rng: &mut impl Rng, (from, to): (f32, f32)) -> f32 {
+    if from == to {
+        from
+    } else {
+        rng.gen_range(from.min(to)..from.max(to))
+    }
+}
+
+/// Both circling formations, read from `assets/orbits.ron`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrbitFormations {
+    pub elips: OrbitFormation,
+    pub point_top: OrbitFormation,
+}
+
+impl OrbitFormations {
+    pub fn load(file: &str) -> Result<Self, DataError> {
+        let path = asset_path(file);
+        let orbits: Self = load_ron(&path)?;
+        orbits
+            .validate()
+            .map_err(|err| DataError::Format(path, err))?;
+        Ok(orbits)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.elips
+            .validate()
+            .map_err(|err| format!("elips: {}", err))?;
+        self.point_top
+            .validate()
+            .map_err(|err| format!("point_top: {}", err))
+    }
+}
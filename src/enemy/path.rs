@@ -0,0 +1,430 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{asset_path, load_ron_dir, DataError};
+use crate::{WinSize, WINDOW_SIZE};
+
+/// Points along a Catmull-Rom or Bezier segment, and along sine dives and loops per turn.
+const SEGMENT_SAMPLES: usize = 16;
+
+/// A path enemies fly along, read from a `.ron` file in `assets/formations/`.
+/// The file name is its name, `FormationKind::Path("name")` uses it.
+///
+/// Points are in pixels of the default window around its center, stretched with the window.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PathFormation {
+    #[serde(skip)]
+    pub name: String,
+    pub shape: PathShape,
+    /// pixels per second
+    pub speed: f32,
+    /// `(fraction of the way, speed multiplier)` pairs in order, straight lines in between.
+    /// Full speed all the way if empty.
+    #[serde(default)]
+    pub speed_curve: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub end: PathEnd,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PathShape {
+    /// straight lines from point to point
+    Waypoints(Vec<(f32, f32)>),
+    /// a smooth curve through all the points
+    CatmullRom(Vec<(f32, f32)>),
+    /// cubic curves: start, two control points, end, two control points, end...
+    Bezier(Vec<(f32, f32)>),
+    /// from `from` to `to`, swinging `amplitude` pixels to the side `waves` times
+    SineDive {
+        from: (f32, f32),
+        to: (f32, f32),
+        amplitude: f32,
+        waves: f32,
+    },
+    /// from `from` to `to` with a loop-the-loop of `radius` pixels `at` (0 to 1) of the way,
+    /// counter-clockwise unless `clockwise`
+    Loop {
+        from: (f32, f32),
+        to: (f32, f32),
+        at: f32,
+        radius: f32,
+        #[serde(default)]
+        clockwise: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PathEnd {
+    /// stays at the last point
+    Stop,
+    /// keeps flying the way it was going and is gone once off screen
+    #[default]
+    Exit,
+    /// starts over from the first point
+    Repeat,
+}
+
+impl PathShape {
+    /// The control points, as they are in the file.
+    pub fn points(&self) -> Vec<Vec2> {
+        match self {
+            PathShape::Waypoints(points)
+            | PathShape::CatmullRom(points)
+            | PathShape::Bezier(points) => points.iter().copied().map(Vec2::from).collect(),
+            PathShape::SineDive { from, to, .. } | PathShape::Loop { from, to, .. } => {
+                vec![Vec2::from(*from), Vec2::from(*to)]
+            }
+        }
+    }
+
+    /// Moves control point `index`, counted as in `points()`.
+    pub fn set_point(&mut self, index: usize, point: Vec2) {
+        let point = (point.x, point.y);
+        match self {
+            PathShape::Waypoints(points)
+            | PathShape::CatmullRom(points)
+            | PathShape::Bezier(points) => points[index] = point,
+            PathShape::SineDive { from, to, .. } | PathShape::Loop { from, to, .. } => {
+                if index == 0 {
+                    *from = point;
+                } else {
+                    *to = point;
+                }
+            }
+        }
+    }
+
+    /// The path as short straight lines, in the file's pixels.
+    pub fn sample(&self) -> Vec<Vec2> {
+        match self {
+            PathShape::Waypoints(points) => points.iter().copied().map(Vec2::from).collect(),
+            PathShape::CatmullRom(points) => {
+                let points: Vec<Vec2> = points.iter().copied().map(Vec2::from).collect();
+                let mut out = vec![points[0]];
+                for i in 0..points.len() - 1 {
+                    // the ends count twice, so the curve goes through them
+                    let p0 = points[i.saturating_sub(1)];
+                    let (p1, p2) = (points[i], points[i + 1]);
+                    let p3 = points[(i + 2).min(points.len() - 1)];
+                    for s in 1..=SEGMENT_SAMPLES {
+                        let t = s as f32 / SEGMENT_SAMPLES as f32;
+                        let (t2, t3) = (t * t, t * t * t);
+                        out.push(
+                            0.5 * (2. * p1
+                                + (p2 - p0) * t
+                                + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+                                + (3. * p1 - p0 - 3. * p2 + p3) * t3),
+                        );
+                    }
+                }
+                out
+            }
+            PathShape::Bezier(points) => {
+                let points: Vec<Vec2> = points.iter().copied().map(Vec2::from).collect();
+                let mut out = vec![points[0]];
+                for segment in points.windows(4).step_by(3) {
+                    let (p0, p1, p2, p3) = (segment[0], segment[1], segment[2], segment[3]);
+                    for s in 1..=SEGMENT_SAMPLES {
+                        let t = s as f32 / SEGMENT_SAMPLES as f32;
+                        let u = 1. - t;
+                        out.push(
+                            u * u * u * p0
+                                + 3. * u * u * t * p1
+                                + 3. * u * t * t * p2
+                                + t * t * t * p3,
+                        );
+                    }
+                }
+                out
+            }
+            PathShape::SineDive {
+                from,
+                to,
+                amplitude,
+                waves,
+            } => {
+                let (from, to) = (Vec2::from(*from), Vec2::from(*to));
+                let side = (to - from).normalize_or_zero().perp();
+                let samples = (SEGMENT_SAMPLES as f32 * waves.max(1.)).ceil() as usize;
+                (0..=samples)
+                    .map(|s| {
+                        let t = s as f32 / samples as f32;
+                        from.lerp(to, t) + side * *amplitude * (TAU * waves * t).sin()
+                    })
+                    .collect()
+            }
+            PathShape::Loop {
+                from,
+                to,
+                at,
+                radius,
+                clockwise,
+            } => {
+                let (from, to) = (Vec2::from(*from), Vec2::from(*to));
+                let start = from.lerp(to, *at);
+                let turn = if *clockwise { -1. } else { 1. };
+                // the circle touches the line where the loop starts
+                let center = start + (to - from).normalize_or_zero().perp() * *radius * turn;
+                let start_angle = (start - center).y.atan2((start - center).x);
+                let mut out = vec![from];
+                for s in 0..=SEGMENT_SAMPLES * 2 {
+                    let angle = start_angle + turn * TAU * s as f32 / (SEGMENT_SAMPLES * 2) as f32;
+                    out.push(center + Vec2::from_angle(angle) * *radius);
+                }
+                out.push(to);
+                out
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let count = self.points().len();
+        match self {
+            PathShape::Waypoints(_) | PathShape::CatmullRom(_) if count < 2 => {
+                Err("a path needs at least 2 points".to_string())
+            }
+            PathShape::Bezier(_) if count < 4 || !(count - 1).is_multiple_of(3) => Err(format!(
+                "a Bezier path needs 3 points per curve and one to start, not {}",
+                count
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl PathFormation {
+    pub fn validate(&self) -> Result<(), String> {
+        self.shape.validate()?;
+        if self.speed <= 0. {
+            return Err(format!("speed {} has to be above 0", self.speed));
+        }
+        if self
+            .speed_curve
+            .windows(2)
+            .any(|pair| pair[1].0 < pair[0].0)
+        {
+            return Err("speed_curve has to be in order".to_string());
+        }
+        if self
+            .speed_curve
+            .iter()
+            .any(|&(_, multiplier)| multiplier <= 0.)
+        {
+            return Err("speed_curve multipliers have to be above 0".to_string());
+        }
+        Ok(())
+    }
+
+    /// Ready to follow in a window of `win_size`.
+    pub fn follower(&self, win_size: &WinSize) -> PathFollower {
+        let stretch = Vec2::new(win_size.w / WINDOW_SIZE.0, win_size.h / WINDOW_SIZE.1);
+        PathFollower::new(
+            self.shape
+                .sample()
+                .into_iter()
+                .map(|point| point * stretch)
+                .collect(),
+            self,
+        )
+    }
+}
+
+/// Moves an enemy along a `PathFormation`, sampled into short straight lines.
+#[derive(Component, Clone)]
+pub struct PathFollower {
+    points: Vec<Vec2>,
+    /// distance from the start to each point
+    lengths: Vec<f32>,
+    pub distance: f32,
+    speed: f32,
+    speed_curve: Vec<(f32, f32)>,
+    end: PathEnd,
+}
+
+impl PathFollower {
+    fn new(points: Vec<Vec2>, formation: &PathFormation) -> Self {
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut total = 0.;
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                total += point.distance(points[i - 1]);
+            }
+            lengths.push(total);
+        }
+        Self {
+            points,
+            lengths,
+            distance: 0.,
+            speed: formation.speed,
+            speed_curve: formation.speed_curve.clone(),
+            end: formation.end,
+        }
+    }
+
+    pub fn start(&self) -> Vec2 {
+        self.points[0]
+    }
+
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.end != PathEnd::Repeat && self.distance >= self.length()
+    }
+
+    /// Position and heading `distance` pixels along, straight on past the end.
+    pub fn at(&self, distance: f32) -> (Vec2, Vec2) {
+        let i = self
+            .lengths
+            .partition_point(|&length| length <= distance)
+            .clamp(1, self.points.len() - 1);
+        let (a, b) = (self.points[i - 1], self.points[i]);
+        let direction = (b - a).normalize_or_zero();
+        (a + direction * (distance - self.lengths[i - 1]), direction)
+    }
+
+    /// Moves `delta` seconds further. True if it jumped back to the start.
+    pub fn advance(&mut self, delta: f32) -> bool {
+        let length = self.length();
+        let fraction = if length > 0. {
+            self.distance / length
+        } else {
+            1.
+        };
+        let speed = self.speed * speed_at(&self.speed_curve, fraction.min(1.));
+        self.distance += speed * delta;
+        match self.end {
+            PathEnd::Stop => {
+                self.distance = self.distance.min(length);
+                false
+            }
+            PathEnd::Exit => false,
+            PathEnd::Repeat if self.distance >= length && length > 0. => {
+                self.distance %= length;
+                true
+            }
+            PathEnd::Repeat => false,
+        }
+    }
+
+    pub fn end(&self) -> PathEnd {
+        self.end
+    }
+}
+
+/// Speed multiplier `fraction` (0 to 1) of the way along.
+pub fn speed_at(curve: &[(f32, f32)], fraction: f32) -> f32 {
+    match curve.iter().position(|&(at, _)| at > fraction) {
+        None => curve.last().map_or(1., |&(_, multiplier)| multiplier),
+        Some(0) => curve[0].1,
+        Some(i) => {
+            let ((a, from), (b, to)) = (curve[i - 1], curve[i]);
+            from + (to - from) * (fraction - a) / (b - a)
+        }
+    }
+}
+
+/// All path formations, by name.
+pub struct PathFormations(pub Vec<PathFormation>);
+
+impl PathFormations {
+    pub fn load(dir: &str) -> Result<Self, DataError> {
+        let formations = load_ron_dir(&asset_path(dir), |formation: &mut PathFormation| {
+            formation.validate()
+        })?
+        .into_iter()
+        .map(|(name, formation)| PathFormation { name, ..formation })
+        .collect();
+        Ok(Self(formations))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PathFormation> {
+        self.0.iter().find(|formation| formation.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formation(shape: PathShape, end: PathEnd) -> PathFormation {
+        PathFormation {
+            name: String::new(),
+            shape,
+            speed: 100.,
+            speed_curve: Vec::new(),
+            end,
+        }
+    }
+
+    fn follower(formation: &PathFormation) -> PathFollower {
+        formation.follower(&WinSize {
+            w: WINDOW_SIZE.0,
+            h: WINDOW_SIZE.1,
+        })
+    }
+
+    /// 100 pixels straight right
+    fn line() -> PathShape {
+        PathShape::Waypoints(vec![(0., 0.), (100., 0.)])
+    }
+
+    #[test]
+    fn speed_curve() {
+        let curve = [(0.2, 2.), (0.6, 1.), (0.8, 0.5)];
+        // at the points
+        assert_eq!(speed_at(&curve, 0.2), 2.);
+        assert_eq!(speed_at(&curve, 0.6), 1.);
+        assert_eq!(speed_at(&curve, 0.8), 0.5);
+        // in between
+        assert!((speed_at(&curve, 0.4) - 1.5).abs() < 1e-6);
+        assert!((speed_at(&curve, 0.7) - 0.75).abs() < 1e-6);
+        // before the first and past the last
+        assert_eq!(speed_at(&curve, 0.), 2.);
+        assert_eq!(speed_at(&curve, 1.), 0.5);
+        assert_eq!(speed_at(&[], 0.5), 1.);
+    }
+
+    #[test]
+    fn exit_keeps_going_past_the_end() {
+        let mut path = follower(&formation(line(), PathEnd::Exit));
+        assert!(!path.advance(1.5));
+        assert!(path.finished());
+        let (position, direction) = path.at(path.distance);
+        assert!((position - Vec2::new(150., 0.)).length() < 1e-3);
+        assert_eq!(direction, Vec2::X);
+    }
+
+    #[test]
+    fn stop_stays_at_the_end() {
+        let mut path = follower(&formation(line(), PathEnd::Stop));
+        assert!(!path.advance(1.5));
+        assert!(path.finished());
+        assert!((path.at(path.distance).0 - Vec2::new(100., 0.)).length() < 1e-3);
+    }
+
+    #[test]
+    fn repeat_wraps_around() {
+        let mut path = follower(&formation(line(), PathEnd::Repeat));
+        assert!(!path.advance(0.5));
+        assert!(path.advance(0.75));
+        assert!(!path.finished());
+        assert!((path.distance - 25.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bezier_point_count() {
+        let bezier = |count: usize| {
+            let points = (0..count).map(|i| (i as f32 * 10., 0.)).collect();
+            formation(PathShape::Bezier(points), PathEnd::Exit).validate()
+        };
+        assert!(bezier(4).is_ok());
+        assert!(bezier(7).is_ok());
+        assert!(bezier(1).is_err());
+        assert!(bezier(5).is_err());
+        assert!(bezier(6).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::Vec3;
+
+/// Sent when a player laser destroys an enemy.
+pub struct EnemyKilled {
+    pub archetype: usize,
+    pub position: Vec3,
+}
+
+/// Sent when a boss or one of its turrets is destroyed, worth `score` points.
+pub struct BossPartDestroyed {
+    pub position: Vec3,
+    pub score: u32,
+}
+
+/// Sent when the player sets off a bomb.
+pub struct BombUsed {
+    pub position: Vec3,
+}
+
+/// Sent when an enemy laser hits the player.
+pub struct PlayerHit {
+    pub position: Vec3,
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::{create_time_channels, TimeSender};
+
+use crate::components::{Enemy, FromEnemy, FromPlayer, Laser, Player};
+use crate::score::{HighScores, Score};
+use crate::simulation::tick_duration;
+use crate::state::{skip_main_menu_system, AppState};
+use crate::wave::WaveEnded;
+use crate::{GameTextures, WinSize, WINDOW_SIZE};
+
+/// Runs the game logic without a window or GPU, on `MinimalPlugins`.
+/// The clock advances a fixed `TIME_STEP` per frame, so a run only depends on
+/// the number of frames and not on how fast the machine is.
+pub struct HeadlessPlugin {
+    pub win_size: (f32, f32),
+    /// start straight in `Playing`, there is nobody to press Enter
+    pub skip_menu: bool,
+}
+
+impl Default for HeadlessPlugin {
+    fn default() -> Self {
+        Self {
+            win_size: WINDOW_SIZE,
+            skip_menu: true,
+        }
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MinimalPlugins).add_plugin(InputPlugin);
+
+        // time_system reads instants from this channel instead of the wall clock
+        let (sender, receiver) = create_time_channels();
+        let startup = app.world.resource::<Time>().startup();
+        sender.0.send(startup).unwrap();
+
+        app.insert_resource(sender)
+            .insert_resource(receiver)
+            .insert_resource(WinSize {
+                w: self.win_size.0,
+                h: self.win_size.1,
+            })
+            .insert_resource(GameTextures {
+                player: Handle::default(),
+                player_laser: Handle::default(),
+                explosion: Handle::default(),
+                background: Handle::default(),
+                font: Handle::default(),
+            })
+            .insert_resource(HighScores::in_memory())
+            .insert_resource(SimulationReport::default())
+            .add_system_to_stage(CoreStage::PostUpdate, simulation_report_system)
+            .add_system_to_stage(CoreStage::Last, headless_clock_system);
+        if self.skip_menu {
+            app.add_startup_system(skip_main_menu_system);
+        }
+    }
+}
+
+/// What happened during a headless run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationReport {
+    pub frames: u32,
+    pub seconds: f64,
+    pub enemies_spawned: u32,
+    pub enemies_destroyed: u32,
+    pub player_lasers: u32,
+    pub enemy_lasers: u32,
+    pub player_deaths: u32,
+    pub waves_ended: u32,
+    pub score: u32,
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "frames:            {}", self.frames)?;
+        writeln!(f, "simulated seconds: {:.2}", self.seconds)?;
+        writeln!(f, "enemies spawned:   {}", self.enemies_spawned)?;
+        writeln!(f, "enemies destroyed: {}", self.enemies_destroyed)?;
+        writeln!(f, "player lasers:     {}", self.player_lasers)?;
+        writeln!(f, "enemy lasers:      {}", self.enemy_lasers)?;
+        writeln!(f, "player deaths:     {}", self.player_deaths)?;
+        writeln!(f, "waves ended:       {}", self.waves_ended)?;
+        write!(f, "score:             {}", self.score)
+    }
+}
+
+/// Ticks an app built with `HeadlessPlugin` for `frames` frames and returns the report.
+pub fn run_frames(app: &mut App, frames: u32) -> SimulationReport {
+    for _ in 0..frames {
+        app.update();
+    }
+    app.world.resource::<SimulationReport>().clone()
+}
+
+fn headless_clock_system(time: Res<Time>, sender: Res<TimeSender>) {
+    let now = time.last_update().unwrap_or_else(|| time.startup());
+    // exactly one simulation tick per frame
+    let _ = sender.0.try_send(now + tick_duration());
+}
+
+fn simulation_report_system(
+    mut report: ResMut<SimulationReport>,
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    score: Res<Score>,
+    spawned_enemies: Query<(), Added<Enemy>>,
+    removed_enemies: RemovedComponents<Enemy>,
+    removed_players: RemovedComponents<Player>,
+    player_lasers: Query<(), (Added<Laser>, With<FromPlayer>)>,
+    enemy_lasers: Query<(), (Added<Laser>, With<FromEnemy>)>,
+    mut wave_events: EventReader<WaveEnded>,
+) {
+    report.frames += 1;
+    report.seconds = time.seconds_since_startup();
+    report.score = report.score.max(score.points);
+    if state.current() != &AppState::Playing {
+        // removals here come from the state cleanup, not from gameplay
+        return;
+    }
+    report.enemies_spawned += spawned_enemies.iter().count() as u32;
+    report.enemies_destroyed += removed_enemies.iter().count() as u32;
+    report.player_lasers += player_lasers.iter().count() as u32;
+    report.enemy_lasers += enemy_lasers.iter().count() as u32;
+    report.player_deaths += removed_players.iter().count() as u32;
+    report.waves_ended += wave_events.iter().count() as u32;
+}
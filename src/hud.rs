@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+use crate::score::Score;
+use crate::state::{despawn_with, AppState};
+use crate::wave::WaveNumber;
+use crate::weapon::WeaponTiers;
+use crate::{GameTextures, PlayerState, WinSize, HUD_FONT_SIZE, WINDOW_SIZE};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(hud_spawn_system))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(hud_score_system)
+                    .with_system(hud_lives_system)
+                    .with_system(hud_wave_system)
+                    .with_system(hud_weapon_system)
+                    .with_system(hud_bombs_system)
+                    .with_system(hud_layout_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(despawn_with::<Hud>));
+    }
+}
+
+#[derive(Component)]
+pub struct Hud;
+#[derive(Component)]
+pub struct HudScore;
+#[derive(Component)]
+pub struct HudLives;
+#[derive(Component)]
+pub struct HudWave;
+#[derive(Component)]
+pub struct HudWeapon;
+#[derive(Component)]
+pub struct HudBombs;
+
+/// HUD is designed for the default window, scale it with the current one.
+fn hud_scale(win_size: &WinSize) -> f32 {
+    (win_size.w / WINDOW_SIZE.0).min(win_size.h / WINDOW_SIZE.1)
+}
+
+fn hud_spawn_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    win_size: Res<WinSize>,
+) {
+    let scale = hud_scale(&win_size);
+    let style = TextStyle {
+        font: game_textures.font.clone(),
+        font_size: HUD_FONT_SIZE * scale,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.),
+                    top: Val::Px(0.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                justify_content: JustifyContent::SpaceBetween,
+                padding: UiRect::all(Val::Px(8. * scale)),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(Hud)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(HudScore);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(HudWave);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(HudWeapon);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(HudBombs);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style))
+                .insert(HudLives);
+        });
+}
+
+// the resources are inserted again on entering `Playing`, so these also fill in a fresh HUD
+
+fn hud_score_system(score: Res<Score>, mut query: Query<&mut Text, With<HudScore>>) {
+    for mut text in query.iter_mut() {
+        if score.is_changed() {
+            text.sections[0].value = if score.combo > 1 {
+                format!("SCORE {} x{}", score.points, score.combo)
+            } else {
+                format!("SCORE {}", score.points)
+            };
+        }
+    }
+}
+
+fn hud_lives_system(player_state: Res<PlayerState>, mut query: Query<&mut Text, With<HudLives>>) {
+    for mut text in query.iter_mut() {
+        if player_state.is_changed() {
+            text.sections[0].value = format!("LIVES {}", player_state.lives);
+        }
+    }
+}
+
+fn hud_wave_system(wave: Res<WaveNumber>, mut query: Query<&mut Text, With<HudWave>>) {
+    for mut text in query.iter_mut() {
+        if wave.is_changed() {
+            text.sections[0].value = format!("WAVE {}", wave.0);
+        }
+    }
+}
+
+fn hud_weapon_system(
+    player_state: Res<PlayerState>,
+    tiers: Res<WeaponTiers>,
+    mut query: Query<&mut Text, With<HudWeapon>>,
+) {
+    for mut text in query.iter_mut() {
+        if player_state.is_changed() {
+            let tier = tiers.get(player_state.weapon_level);
+            text.sections[0].value = format!("WEAPON {}", tier.name.to_uppercase());
+        }
+    }
+}
+
+fn hud_bombs_system(player_state: Res<PlayerState>, mut query: Query<&mut Text, With<HudBombs>>) {
+    for mut text in query.iter_mut() {
+        if player_state.is_changed() {
+            text.sections[0].value = format!("BOMBS {}", player_state.bombs);
+        }
+    }
+}
+
+fn hud_layout_system(
+    win_size: Res<WinSize>,
+    mut root_query: Query<&mut Style, With<Hud>>,
+    mut text_query: Query<
+        &mut Text,
+        Or<(
+            With<HudScore>,
+            With<HudLives>,
+            With<HudWave>,
+            With<HudWeapon>,
+            With<HudBombs>,
+        )>,
+    >,
+) {
+    if !win_size.is_changed() {
+        return;
+    }
+    let scale = hud_scale(&win_size);
+    for mut style in root_query.iter_mut() {
+        style.padding = UiRect::all(Val::Px(8. * scale));
+    }
+    for mut text in text_query.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.font_size = HUD_FONT_SIZE * scale;
+        }
+    }
+}
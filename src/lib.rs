@@ -0,0 +1,477 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+use std::collections::HashSet;
+
+use bevy::{
+    prelude::*,
+    window::WindowResized,
+};
+use components::{
+    Enemy, Explosion, ExplosionScale, ExplosionTimer, ExplosionToSpawn, FromEnemy, FromPlayer, Laser, Movable,
+    Player, Velocity, Background, Invulnerable, Damage, Health, HitFlash,
+};
+pub use enemy::EnemyPlugin;
+use enemy::archetype::EnemyArchetypes;
+pub use player::PlayerPlugin;
+use boss::BossPlugin;
+use boss::kind::BossKinds;
+use collision::{CollisionEvent, CollisionPlugin, SpentProjectiles};
+use controls::ControlsPlugin;
+use events::{BombUsed, EnemyKilled, PlayerHit};
+use hud::HudPlugin;
+use pattern::PatternPlugin;
+use pickup::PickupPlugin;
+use rng::RngPlugin;
+use score::ScorePlugin;
+use simulation::{Interpolated, SimTime, SimulationPlugin, SimulationStage};
+use state::{despawn_with, AppState, GameStatePlugin, GameStep, InGameStep};
+use wave::WavePlugin;
+use weapon::WeaponPlugin;
+
+/* #region constlar */
+pub const PLAYER_LASER: &str = "laserBlue15.png";
+pub const PLAYER_LASER_SIZE: (f32, f32) = (9., 57.);
+pub const PLAYER_SPRITE: &str = "playerShip2_blue.png";
+pub const PLAYER_SIZE: (f32, f32) = (112., 75.);
+/// the only part of the player ship enemy fire can hit, graze everything else
+pub const PLAYER_CORE_RADIUS: f32 = 8.;
+/// collide with the ship's pixels instead of the core
+pub const PLAYER_PIXEL_PERFECT: bool = false;
+pub const PLAYER_LASER_DAMAGE: u32 = 1;
+/// shots per second while Fire is held
+pub const PLAYER_FIRE_RATE: f32 = 8.;
+pub const PLAYER_RESPAWN_DELAY : f32 = 2.;
+pub const PLAYER_LIVES: u32 = 3;
+pub const PLAYER_INVULNERABLE_TIME: f32 = 2.5;
+pub const PLAYER_BLINK_PERIOD: f32 = 0.1;
+pub const PLAYER_WEAPONS: &str = "weapons.ron";
+pub const PLAYER_BOMBS: u32 = 3;
+pub const PLAYER_BOMBS_MAX: u32 = 5;
+/// taken from every enemy on screen
+pub const BOMB_DAMAGE: u32 = 5;
+pub const BOMB_INVULNERABLE_TIME: f32 = 2.;
+pub const BOMB_EXPLOSION_SCALE: f32 = 5.;
+/// speed multiplier while `Focus` is held
+pub const PLAYER_FOCUS_SPEED: f32 = 0.5;
+
+/// pickups are drawn as squares this big
+pub const PICKUP_SIZE: f32 = 20.;
+/// in `Velocity` units per second
+pub const PICKUP_GRAVITY: f32 = 1.2;
+pub const PICKUP_FALL_SPEED: f32 = 0.25;
+
+pub const ENEMY_ARCHETYPES_DIR: &str = "enemies";
+pub const PATTERNS_FILE: &str = "patterns.ron";
+pub const FORMATIONS_DIR: &str = "formations";
+pub const ORBITS_FILE: &str = "orbits.ron";
+pub const BOSS_DIR: &str = "bosses";
+/// pixels per second, however far the phase movement wants it to go
+pub const BOSS_MAX_SPEED: f32 = 300.;
+pub const BOSS_BAR_SIZE: (f32, f32) = (300., 8.);
+pub const LEVEL_SCRIPT: &str = "levels/level1.ron";
+
+pub const BACKGROUND_SPRITE: &str = "desert-backgorund-looped.png";
+pub const BACKGROUND_HEIGHT: f32= 608.;
+
+pub const EXPLOSION_SHEET: &str = "exp2_0.png";
+pub const EXPLOSION_LEN: usize = 16;
+
+pub const COMBO_WINDOW: f64 = 2.;
+pub const COMBO_MAX: u32 = 8;
+pub const HIGH_SCORE_COUNT: usize = 10;
+pub const HIGH_SCORE_FILE: &str = "highscores.txt";
+pub const CONTROLS_FILE: &str = "controls.ron";
+
+pub const FONT: &str = "fonts/DejaVuSans-Bold.ttf";
+pub const HUD_FONT_SIZE: f32 = 20.;
+
+pub const COLLISION_CELL_SIZE: f32 = 64.;
+
+pub const SPRITE_SCALE: f32 = 0.5;
+pub const TIME_STEP: f32 = 1. / 60.;
+pub const BASE_SPEED: f32 = 500.;
+pub const WINDOW_SIZE: (f32, f32) = (598., 676.);
+/* #endregion */
+
+//mod
+pub mod boss;
+pub mod collision;
+pub mod components;
+pub mod controls;
+pub mod data;
+pub mod enemy;
+pub mod events;
+pub mod headless;
+pub mod hud;
+pub mod pattern;
+pub mod pickup;
+pub mod player;
+pub mod replay;
+pub mod rng;
+pub mod score;
+pub mod simulation;
+pub mod state;
+pub mod wave;
+pub mod weapon;
+
+/* #region  structlar */
+pub struct WinSize {
+    pub w: f32,
+    pub h: f32,
+}
+pub struct GameTextures {
+    pub player: Handle<Image>,
+    pub player_laser: Handle<Image>,
+    pub explosion: Handle<TextureAtlas>,
+    pub background: Handle<Image>,
+    pub font: Handle<Font>,
+}
+pub struct PlayerState {
+    pub on: bool,       //alive
+    pub last_shot: f64, //-1 if not shot
+    pub lives: u32,
+    /// counts from 1, see `weapon::WeaponTiers`
+    pub weapon_level: u32,
+    pub bombs: u32,
+}
+impl Default for PlayerState {
+    fn default() -> Self {
+        Self {
+            on: false,
+            last_shot: -1.,
+            lives: PLAYER_LIVES,
+            weapon_level: 1,
+            bombs: PLAYER_BOMBS,
+        }
+    }
+}
+impl PlayerState {
+    pub fn shot(&mut self, time: f64) {
+        self.on = false;
+        self.last_shot = time;
+        self.lives = self.lives.saturating_sub(1);
+        // dying costs a weapon tier
+        self.weapon_level = self.weapon_level.saturating_sub(1).max(1);
+    }
+    pub fn spawned(&mut self) {
+        self.on = true;
+        self.last_shot = -1.;
+    }
+}
+/* #endregion */
+
+/// The whole game: player, enemies, collisions, explosions and the scrolling background.
+/// Expects `WinSize` and `GameTextures` to be provided, either by `WindowedPlugin`
+/// or by `headless::HeadlessPlugin`.
+pub struct ShooterPlugin;
+
+impl Plugin for ShooterPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<EnemyKilled>()
+            .add_event::<PlayerHit>()
+            .add_event::<BombUsed>()
+            .add_plugin(ControlsPlugin)
+            .add_plugin(GameStatePlugin)
+            .add_plugin(RngPlugin)
+            .add_plugin(SimulationPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(WeaponPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(PatternPlugin)
+            .add_plugin(EnemyPlugin)
+            .add_plugin(BossPlugin)
+            .add_plugin(WavePlugin)
+            .add_plugin(PickupPlugin)
+            .add_plugin(ScorePlugin)
+            .add_plugin(HudPlugin)
+            .add_startup_system(background_spawn)
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_system(movable_system.in_step(GameStep::Movement))
+                    .with_system(backgorund_movement.in_step(GameStep::Movement))
+                    .with_system(player_laser_hit_enemy_system.in_step(GameStep::PlayerHits))
+                    .with_system(enemy_laser_hit_player_system.in_step(GameStep::EnemyHits))
+                    .with_system(hit_flash_system.in_step(GameStep::HitFlash)),
+            )
+            // explosions are only for show, they animate with the frames
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(explosion_to_spawn_system)
+                    .with_system(explosion_animation_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Playing)
+                    .with_system(despawn_with::<Laser>)
+                    .with_system(despawn_with::<Explosion>)
+                    .with_system(despawn_with::<ExplosionToSpawn>),
+            );
+    }
+}
+
+/// Opens the game window with `DefaultPlugins`, spawns the camera and loads the textures.
+pub struct WindowedPlugin;
+
+impl Plugin for WindowedPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
+            .insert_resource(WindowDescriptor {
+                title: "ilk oyun".to_string(),
+                width: WINDOW_SIZE.0,
+                height: WINDOW_SIZE.1,
+                ..Default::default()
+            })
+            .add_plugins(DefaultPlugins)
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_system)
+            // before anything reads WinSize, so a frame never sees two sizes
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                window_resize_system.label(WindowResizeSystem),
+            );
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut windows: ResMut<Windows>,
+    mut archetypes: ResMut<EnemyArchetypes>,
+    mut bosses: ResMut<BossKinds>,
+) {
+    //kamera
+    commands.spawn_bundle(Camera2dBundle::default());
+    //windows
+    let window = windows.get_primary_mut().unwrap();
+    let (win_w, win_h) = (window.width(), window.height());
+    //winsize
+    let win_size = WinSize { w: win_w, h: win_h };
+    commands.insert_resource(win_size);
+    //Patlama
+    let texture_handle = asset_server.load(EXPLOSION_SHEET);
+    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(64., 64.), 4, 4);
+    let explosion = texture_atlases.add(texture_atlas);
+    //GameTextures
+    let game_textures = GameTextures {
+        player: asset_server.load(PLAYER_SPRITE),
+        player_laser: asset_server.load(PLAYER_LASER),
+        explosion,
+        background: asset_server.load(BACKGROUND_SPRITE),
+        font: asset_server.load(FONT),
+    };
+    commands.insert_resource(game_textures);
+    archetypes.load_textures(&asset_server);
+    bosses.load_textures(&asset_server);
+}
+
+/// Replays run `.after(WindowResizeSystem)`, their recorded window size wins.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct WindowResizeSystem;
+
+fn window_resize_system(mut win_size: ResMut<WinSize>, mut resize_events: EventReader<WindowResized>) {
+    if let Some(resized) = resize_events.iter().last() {
+        win_size.w = resized.width;
+        win_size.h = resized.height;
+    }
+}
+
+fn movable_system(
+    mut commands: Commands,
+    win_size: Res<WinSize>,
+    mut query: Query<(Entity, &Velocity, &mut Transform, &Movable, Option<&mut Interpolated>)>,
+) {
+    for (entity, velocity, mut transform, movable, interpolated) in query.iter_mut() {
+        let translation = &mut transform.translation;
+        translation.x += velocity.x * TIME_STEP * BASE_SPEED;
+        translation.y += velocity.y * TIME_STEP * BASE_SPEED;
+        let mut wrapped = false;
+        if translation.x > win_size.w / 2. + 50. {
+            translation.x = -(win_size.w / 2. + 50.);
+            wrapped = true;
+        }
+        if translation.x < -(win_size.w / 2. + 50.) {
+            translation.x = win_size.w / 2. + 50.;
+            wrapped = true;
+        }
+        if let (true, Some(mut interpolated)) = (wrapped, interpolated) {
+            interpolated.teleport(*translation);
+        }
+        if movable.auto_despawn {
+            const MARGIN: f32 = 1000.;
+            if translation.y > win_size.h / 2. + MARGIN
+                || translation.y < -win_size.h / 2. - MARGIN
+                || translation.x > win_size.w / 2. + MARGIN
+                || translation.x < -win_size.w / 2. - MARGIN
+            {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+fn enemy_laser_hit_player_system(
+    mut commands: Commands,
+    mut player_state : ResMut<PlayerState>,
+    mut hit_events: EventWriter<PlayerHit>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut bomb_events: EventReader<BombUsed>,
+    sim_time : Res<SimTime>,
+    laser_query: Query<(), (With<Laser>, With<FromEnemy>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Invulnerable>)>,
+) {
+    // the blast covers the player on the tick it goes off, before `Invulnerable` lands
+    // and the lasers it cleared are gone
+    if bomb_events.iter().count() > 0 {
+        collision_events.clear();
+        return;
+    }
+    for collision in collision_events.iter() {
+        if let (Ok(player_tf), Ok(())) = (player_query.get(collision.a), laser_query.get(collision.b)) {
+            commands.entity(collision.a).despawn();
+            player_state.shot(sim_time.seconds());
+            hit_events.send(PlayerHit {
+                position: player_tf.translation,
+            });
+            commands.entity(collision.b).despawn();
+            commands
+                .spawn()
+                .insert(ExplosionToSpawn(player_tf.translation));
+            break;
+        }
+    }
+}
+
+fn player_laser_hit_enemy_system(
+    mut commands: Commands,
+    mut killed_events: EventWriter<EnemyKilled>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut spent: ResMut<SpentProjectiles>,
+    laser_query: Query<&Damage, (With<Laser>, With<FromPlayer>)>,
+    mut enemy_query: Query<(&Transform, &Enemy, &mut Health)>,
+) {
+    let mut despawned_enemies: HashSet<Entity> = HashSet::new();
+    for collision in collision_events.iter() {
+        let (laser_entity, enemy_entity) = (collision.a, collision.b);
+        if spent.0.contains(&laser_entity)
+            || despawned_enemies.contains(&enemy_entity)
+        {
+            continue;
+        }
+        if let (Ok(damage), Ok((enemy_tf, enemy, mut health))) =
+            (laser_query.get(laser_entity), enemy_query.get_mut(enemy_entity))
+        {
+            // a bomb got it earlier this tick, it only waits to be despawned
+            if health.current == 0 {
+                continue;
+            }
+            commands.entity(laser_entity).despawn();
+            spent.0.insert(laser_entity);
+
+            if !health.take(damage.0) {
+                commands.entity(enemy_entity).insert(HitFlash::default());
+                continue;
+            }
+            commands.entity(enemy_entity).despawn();
+            despawned_enemies.insert(enemy_entity);
+            killed_events.send(EnemyKilled {
+                archetype: enemy.archetype,
+                position: enemy_tf.translation,
+            });
+            commands
+                .spawn()
+                .insert(ExplosionToSpawn(enemy_tf.translation));
+        }
+    }
+}
+
+fn hit_flash_system(
+    mut commands: Commands,
+    sim_time: Res<SimTime>,
+    mut query: Query<(Entity, &mut HitFlash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        flash.0.tick(sim_time.delta());
+        if flash.0.finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<HitFlash>();
+        } else {
+            sprite.color = Color::rgb(1., 0.3, 0.3);
+        }
+    }
+}
+
+fn explosion_to_spawn_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    query: Query<(Entity, &ExplosionToSpawn, Option<&ExplosionScale>)>,
+) {
+    for (explosion_spawn_entity, explosion_to_spawn, scale) in query.iter() {
+        let scale = scale.map_or(1., |scale| scale.0);
+        // spawn the explosion sprite
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: game_textures.explosion.clone(),
+                transform: Transform {
+                    translation: explosion_to_spawn.0,
+                    scale: Vec3::new(scale, scale, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Explosion)
+            .insert(ExplosionTimer::default());
+
+        // despawn the explosionToSpawn
+        commands.entity(explosion_spawn_entity).despawn();
+    }
+}
+
+fn explosion_animation_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ExplosionTimer, &mut TextureAtlasSprite), With<Explosion>>,
+) {
+    for (entity, mut timer, mut sprite) in query.iter_mut() {
+        timer.0.tick(time.delta());
+        if timer.0.finished() {
+            sprite.index += 1;
+            if sprite.index >= EXPLOSION_LEN {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+
+fn background_spawn(mut commands: Commands,game_textures: Res<GameTextures>){
+    let background_sprite = &game_textures.background;
+    let bk_y = BACKGROUND_HEIGHT / 2. * 2.36 ;
+    let mut spawn_background = |bk:f32| {
+        commands
+        .spawn_bundle(SpriteBundle {
+            texture : background_sprite.clone(),
+            transform : Transform {
+                translation : Vec3::new(0.,bk,0.),
+                scale : Vec3::new(2.36, 2.36, 1.),
+                ..Default::default()
+            }, 
+            ..Default::default()
+        })
+        .insert(Background)
+        .insert(Movable {auto_despawn :false})
+        .insert(Velocity {x:0.,y:-0.2});};
+    spawn_background(bk_y);
+    spawn_background(-bk_y);   
+}
+fn backgorund_movement(mut query: Query<(&mut Transform, Option<&mut Interpolated>),With<Background>>,win_size: Res<WinSize>){
+    for (mut transform, interpolated) in query.iter_mut(){
+        let translation = &mut transform.translation;
+        if translation.y < - BACKGROUND_HEIGHT * 2.36 / 2.0 -win_size.h / 2.0{
+            translation.y = 2.0 * BACKGROUND_HEIGHT * 2.36 - win_size.h * 1.65;
+            if let Some(mut interpolated) = interpolated {
+                interpolated.teleport(*translation);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::time::Duration;
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::boss::kind::BossKinds;
+use crate::components::{Velocity, Weapon};
+use crate::data::{asset_path, load_ron, DataError};
+use crate::enemy::archetype::{EnemyArchetypes, EnemyWeapon};
+use crate::enemy::spawn_enemy_laser;
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{GameStep, InGameStep};
+use crate::PATTERNS_FILE;
+
+/// Bullet patterns for enemy and boss fire, read by name from `assets/patterns.ron`.
+pub struct PatternPlugin;
+
+impl Plugin for PatternPlugin {
+    fn build(&self, app: &mut App) {
+        let patterns = Patterns::load(PATTERNS_FILE)
+            .unwrap_or_else(|err| panic!("could not load bullet patterns: {}", err));
+        app.insert_resource(patterns).add_system_set_to_stage(
+            SimulationStage,
+            SystemSet::new().with_system(
+                bullet_queue_system
+                    .in_step(GameStep::EnemyFire)
+                    .label(BulletQueueSystem),
+            ),
+        );
+    }
+}
+
+/// Stream bullets due this tick go out before any new volley.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub struct BulletQueueSystem;
+
+/// One volley, built up from the simple ones. Angles are in degrees, counter-clockwise,
+/// around straight down unless something aimed it.
+#[derive(Clone, Debug, Deserialize)]
+pub enum Pattern {
+    Single,
+    /// `count` bullets fanned out over `spread` degrees
+    Spread {
+        count: u32,
+        spread: f32,
+    },
+    /// `count` bullets evenly all around
+    Ring {
+        count: u32,
+    },
+    /// `pattern` around the direction to the player at the time of firing
+    Aimed(Box<Pattern>),
+    Turn {
+        angle: f32,
+        pattern: Box<Pattern>,
+    },
+    /// `pattern` turned `step` degrees further every volley
+    Spiral {
+        step: f32,
+        pattern: Box<Pattern>,
+    },
+    /// `pattern` `count` times, `delay` seconds apart
+    Stream {
+        count: u32,
+        delay: f32,
+        pattern: Box<Pattern>,
+    },
+    /// `pattern` with its bullets `factor` times as fast
+    Speed {
+        factor: f32,
+        pattern: Box<Pattern>,
+    },
+    /// all of them at once
+    Layers(Vec<Pattern>),
+    /// another pattern of the file, by name
+    Use(String),
+}
+
+/// One bullet of a volley.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bullet {
+    /// seconds after the volley
+    pub delay: f32,
+    /// radians, counter-clockwise from the x axis
+    pub angle: f32,
+    /// times the weapon's speed
+    pub speed: f32,
+}
+
+impl Bullet {
+    pub fn velocity(&self, speed: f32) -> Velocity {
+        let velocity = Vec2::from_angle(self.angle) * self.speed * speed;
+        Velocity {
+            x: velocity.x,
+            y: velocity.y,
+        }
+    }
+}
+
+/// Deep enough for any sane pattern, a pattern using itself stops here.
+const MAX_DEPTH: u32 = 16;
+
+impl Pattern {
+    fn emit(
+        &self,
+        patterns: &Patterns,
+        bullet: Bullet,
+        aim: Option<f32>,
+        volley: u32,
+        out: &mut Vec<Bullet>,
+    ) {
+        let turned = |angle: f32| Bullet {
+            angle: bullet.angle + angle,
+            ..bullet
+        };
+        match self {
+            Pattern::Single => out.push(bullet),
+            Pattern::Spread { count, spread } => {
+                for i in 0..*count {
+                    let t = if *count > 1 {
+                        i as f32 / (count - 1) as f32 - 0.5
+                    } else {
+                        0.
+                    };
+                    out.push(turned(spread.to_radians() * t));
+                }
+            }
+            Pattern::Ring { count } => {
+                for i in 0..*count {
+                    out.push(turned(TAU * i as f32 / *count as f32));
+                }
+            }
+            Pattern::Aimed(pattern) => {
+                let angle = aim.unwrap_or(bullet.angle);
+                pattern.emit(patterns, Bullet { angle, ..bullet }, aim, volley, out);
+            }
+            Pattern::Turn { angle, pattern } => {
+                pattern.emit(patterns, turned(angle.to_radians()), aim, volley, out);
+            }
+            Pattern::Spiral { step, pattern } => {
+                let angle = (step * volley as f32).to_radians();
+                pattern.emit(patterns, turned(angle), aim, volley, out);
+            }
+            Pattern::Stream {
+                count,
+                delay,
+                pattern,
+            } => {
+                for i in 0..*count {
+                    let bullet = Bullet {
+                        delay: bullet.delay + delay * i as f32,
+                        ..bullet
+                    };
+                    pattern.emit(patterns, bullet, aim, volley, out);
+                }
+            }
+            Pattern::Speed { factor, pattern } => {
+                let bullet = Bullet {
+                    speed: bullet.speed * factor,
+                    ..bullet
+                };
+                pattern.emit(patterns, bullet, aim, volley, out);
+            }
+            Pattern::Layers(layers) => {
+                for pattern in layers {
+                    pattern.emit(patterns, bullet, aim, volley, out);
+                }
+            }
+            Pattern::Use(name) => {
+                // checked when loaded
+                if let Some(pattern) = patterns.0.get(name) {
+                    pattern.emit(patterns, bullet, aim, volley, out);
+                }
+            }
+        }
+    }
+
+    fn check(&self, patterns: &Patterns, depth: u32) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err("patterns nested too deep, does one use itself?".to_string());
+        }
+        match self {
+            Pattern::Single | Pattern::Spread { .. } | Pattern::Ring { .. } => Ok(()),
+            Pattern::Aimed(pattern)
+            | Pattern::Turn { pattern, .. }
+            | Pattern::Spiral { pattern, .. }
+            | Pattern::Speed { pattern, .. } => pattern.check(patterns, depth + 1),
+            Pattern::Stream { delay, pattern, .. } => {
+                if *delay < 0. {
+                    return Err(format!("stream delay {} is below 0", delay));
+                }
+                pattern.check(patterns, depth + 1)
+            }
+            Pattern::Layers(layers) => layers
+                .iter()
+                .try_for_each(|pattern| pattern.check(patterns, depth + 1)),
+            Pattern::Use(name) => patterns
+                .0
+                .get(name)
+                .ok_or_else(|| format!("unknown pattern \"{}\"", name))?
+                .check(patterns, depth + 1),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PatternFile {
+    patterns: HashMap<String, Pattern>,
+}
+
+/// All patterns by name.
+pub struct Patterns(pub HashMap<String, Pattern>);
+
+impl Patterns {
+    pub fn load(file: &str) -> Result<Self, DataError> {
+        let path = asset_path(file);
+        let PatternFile { patterns } = load_ron(&path)?;
+        let patterns = Self(patterns);
+        for (name, pattern) in patterns.0.iter() {
+            pattern
+                .check(&patterns, 0)
+                .map_err(|err| DataError::Format(path.clone(), format!("{}: {}", name, err)))?;
+        }
+        Ok(patterns)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The bullets of volley number `volley` (from 0) of the named pattern.
+    /// `aim` is the direction to the player, if there is one.
+    pub fn volley(&self, name: &str, aim: Option<Vec2>, volley: u32) -> Vec<Bullet> {
+        let mut bullets = Vec::new();
+        if let Some(pattern) = self.0.get(name) {
+            let down = Bullet {
+                delay: 0.,
+                angle: -FRAC_PI_2,
+                speed: 1.,
+            };
+            let aim = aim.map(|aim| aim.y.atan2(aim.x));
+            pattern.emit(self, down, aim, volley, &mut bullets);
+        }
+        bullets
+    }
+}
+
+/// Cooldown and volley count of one enemy or boss weapon, the count turns spirals.
+#[derive(Component)]
+pub struct Gun {
+    pub weapon: Weapon,
+    pub volleys: u32,
+}
+
+impl Gun {
+    /// The first volley a full cooldown after it shows up.
+    pub fn new(weapon: &EnemyWeapon) -> Self {
+        Self {
+            weapon: Weapon {
+                cooldown: Timer::from_seconds(1. / weapon.fire_rate, false),
+                auto_fire: true,
+            },
+            volleys: 0,
+        }
+    }
+
+    /// The number of the volley to fire now, if it is time for one.
+    pub fn trigger(&mut self, delta: Duration) -> Option<u32> {
+        // enemies never let go of the trigger
+        if !self.weapon.trigger(delta, true, false) {
+            return None;
+        }
+        self.volleys += 1;
+        Some(self.volleys - 1)
+    }
+}
+
+/// Where a weapon is in the loaded enemies and bosses, so queued bullets do not each
+/// carry a copy of it.
+#[derive(Clone, Copy, Debug)]
+pub enum WeaponRef {
+    /// index into `EnemyArchetypes`
+    Enemy(usize),
+    /// index into `BossKinds`, phase and attack
+    BossAttack {
+        kind: usize,
+        phase: usize,
+        attack: usize,
+    },
+    /// index into `BossKinds` and turret
+    Turret { kind: usize, turret: usize },
+}
+
+impl WeaponRef {
+    pub fn get<'a>(
+        self,
+        archetypes: &'a EnemyArchetypes,
+        bosses: &'a BossKinds,
+    ) -> Option<&'a EnemyWeapon> {
+        match self {
+            WeaponRef::Enemy(archetype) => archetypes.0[archetype].weapon.as_ref(),
+            WeaponRef::BossAttack {
+                kind,
+                phase,
+                attack,
+            } => Some(&bosses.0[kind].phases[phase].attacks[attack]),
+            WeaponRef::Turret { kind, turret } => Some(&bosses.0[kind].turrets[turret].weapon),
+        }
+    }
+}
+
+/// Bullets of streams still to come, with the muzzle they leave from. They leave from
+/// wherever the shooter is by then, and never if it is destroyed first.
+#[derive(Component, Default)]
+pub struct BulletQueue(Vec<(Bullet, Vec2, WeaponRef)>);
+
+/// Fires one volley of `weapon`'s pattern from `muzzle` (from the shooter's center),
+/// at `target` if aimed. Bullets with a delay wait in `queue`, `source` is where they
+/// find `weapon` again.
+pub fn fire_pattern(
+    commands: &mut Commands,
+    patterns: &Patterns,
+    weapon: &EnemyWeapon,
+    source: WeaponRef,
+    volley: u32,
+    shooter: &Transform,
+    muzzle: Vec2,
+    target: Option<Vec2>,
+    queue: &mut BulletQueue,
+) {
+    let from = shooter.translation.xy() + muzzle;
+    let aim = target.map(|target| target - from);
+    for bullet in patterns.volley(&weapon.pattern, aim, volley) {
+        if bullet.delay > 0. {
+            queue.0.push((bullet, muzzle, source));
+        } else {
+            let velocity = bullet.velocity(weapon.speed);
+            spawn_enemy_laser(commands, weapon, from.extend(6.), velocity);
+        }
+    }
+}
+
+fn bullet_queue_system(
+    mut commands: Commands,
+    sim_time: Res<SimTime>,
+    archetypes: Res<EnemyArchetypes>,
+    bosses: Res<BossKinds>,
+    mut query: Query<(&mut BulletQueue, &Transform)>,
+) {
+    for (mut queue, transform) in query.iter_mut() {
+        for (bullet, muzzle, source) in queue.0.iter_mut() {
+            bullet.delay -= sim_time.delta_seconds();
+            if bullet.delay <= 0. {
+                let weapon = match source.get(&archetypes, &bosses) {
+                    Some(weapon) => weapon,
+                    None => continue,
+                };
+                let from = (transform.translation.xy() + *muzzle).extend(6.);
+                spawn_enemy_laser(&mut commands, weapon, from, bullet.velocity(weapon.speed));
+            }
+        }
+        queue.0.retain(|(bullet, _, _)| bullet.delay > 0.);
+    }
+}
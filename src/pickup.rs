@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::collision::{CollisionEvent, CollisionLayer, Hitbox};
+use crate::components::{Movable, SpriteSize, Velocity};
+use crate::enemy::archetype::EnemyArchetypes;
+use crate::events::EnemyKilled;
+use crate::rng::GameRng;
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{despawn_with, AppState, GameStep, InGameStep};
+use crate::weapon::WeaponTiers;
+use crate::{PlayerState, PICKUP_FALL_SPEED, PICKUP_GRAVITY, PICKUP_SIZE, PLAYER_BOMBS_MAX};
+
+/// Power-ups dropped by destroyed enemies, and the player picking them up.
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            SimulationStage,
+            SystemSet::new()
+                .with_system(pickup_movement_system.in_step(GameStep::Steering))
+                .with_system(pickup_collect_system.in_step(GameStep::Pickups))
+                .with_system(
+                    pickup_drop_system
+                        .in_step(GameStep::Pickups)
+                        .after(pickup_collect_system),
+                ),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(despawn_with::<Pickup>));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum PickupKind {
+    /// one weapon tier up
+    Weapon,
+    /// one more bomb
+    Bomb,
+}
+
+impl PickupKind {
+    fn color(self) -> Color {
+        match self {
+            PickupKind::Weapon => Color::rgb(1., 0.6, 0.1),
+            PickupKind::Bomb => Color::rgb(0.3, 1., 0.4),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Pickup(pub PickupKind);
+
+/// A pickup an enemy leaves behind with probability `chance` (0 to 1).
+#[derive(Clone, Debug, Deserialize)]
+pub struct Drop {
+    pub pickup: PickupKind,
+    pub chance: f32,
+}
+
+pub fn spawn_pickup(commands: &mut Commands, kind: PickupKind, position: Vec3) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(position.x, position.y, 8.),
+            ..Default::default()
+        })
+        .insert(Pickup(kind))
+        .insert(SpriteSize::from((PICKUP_SIZE, PICKUP_SIZE)))
+        // a bit bigger than the sprite, the player only has a small core
+        .insert(Hitbox::circle(PICKUP_SIZE))
+        .insert(CollisionLayer::Pickup)
+        .insert(Movable { auto_despawn: true })
+        // pops up out of the wreck, then falls down the screen
+        .insert(Velocity { x: 0., y: 0.4 });
+}
+
+fn pickup_drop_system(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    archetypes: Res<EnemyArchetypes>,
+    mut killed_events: EventReader<EnemyKilled>,
+) {
+    for killed in killed_events.iter() {
+        // at most one pickup per enemy, the first drop that comes up
+        let dropped = archetypes.0[killed.archetype]
+            .drops
+            .iter()
+            .find(|drop| rng.gen::<f32>() < drop.chance);
+        if let Some(drop) = dropped {
+            spawn_pickup(&mut commands, drop.pickup, killed.position);
+        }
+    }
+}
+
+fn pickup_movement_system(
+    sim_time: Res<SimTime>,
+    mut query: Query<(&mut Velocity, &mut Transform), With<Pickup>>,
+) {
+    for (mut velocity, mut transform) in query.iter_mut() {
+        velocity.y = (velocity.y - PICKUP_GRAVITY * sim_time.delta_seconds()).max(-PICKUP_FALL_SPEED);
+        transform.rotate_z(3. * sim_time.delta_seconds());
+    }
+}
+
+fn pickup_collect_system(
+    mut commands: Commands,
+    mut player_state: ResMut<PlayerState>,
+    tiers: Res<WeaponTiers>,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickup_query: Query<&Pickup>,
+) {
+    for collision in collision_events.iter() {
+        // the player is the lower layer, the pickup comes second
+        let pickup = match pickup_query.get(collision.b) {
+            Ok(pickup) => pickup,
+            Err(_) => continue,
+        };
+        match pickup.0 {
+            PickupKind::Weapon => {
+                if player_state.weapon_level < tiers.max_level() {
+                    player_state.weapon_level += 1;
+                }
+            }
+            PickupKind::Bomb => {
+                player_state.bombs = (player_state.bombs + 1).min(PLAYER_BOMBS_MAX);
+            }
+        }
+        commands.entity(collision.b).despawn();
+    }
+}
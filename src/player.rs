@@ -1,7 +1,13 @@
-use crate::{GameTextures,WinSize,PLAYER_SIZE,SPRITE_SCALE, TIME_STEP, BASE_SPEED, PLAYER_LASER, PLAYER_LASER_SIZE, PlayerState, PLAYER_RESPAWN_DELAY};
+use crate::{GameTextures,WinSize,PLAYER_SIZE,SPRITE_SCALE, PLAYER_LASER, PLAYER_LASER_SIZE, PlayerState, PLAYER_RESPAWN_DELAY, PLAYER_INVULNERABLE_TIME, PLAYER_BLINK_PERIOD, PLAYER_LASER_DAMAGE, PLAYER_CORE_RADIUS, PLAYER_PIXEL_PERFECT, PLAYER_SPRITE, PLAYER_FOCUS_SPEED, PLAYER_FIRE_RATE, BOMB_DAMAGE, BOMB_INVULNERABLE_TIME, BOMB_EXPLOSION_SCALE};
+use std::time::Duration;
+use crate::controls::{Action, ActionState};
+use crate::events::{BombUsed, EnemyKilled};
+use crate::weapon::{Homing, WeaponTiers};
+use crate::collision::{AlphaMask, CollisionLayer, Hitbox, PixelPerfect};
 use crate::components::*;
-use bevy::time::FixedTimestep;
-use bevy::{prelude::*, ecs::query};
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{despawn_with, AppState, GameStep, InGameStep};
+use bevy::prelude::*;
 
 pub struct PlayerPlugin; 
 
@@ -9,100 +15,249 @@ impl Plugin for PlayerPlugin{
     fn build(&self, app : &mut App){
         app
         .insert_resource(PlayerState::default())
+        .insert_resource(PlayerInput::default())
+        .insert_resource(PlayerMasks {
+            ship: AlphaMask::load_or_warn(PLAYER_SPRITE),
+            laser: AlphaMask::load_or_warn(PLAYER_LASER),
+        })
         .add_system_set(
+            SystemSet::on_enter(AppState::Playing)
+                .with_system(|mut commands: Commands| commands.insert_resource(PlayerState::default()))
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(player_input_system)
+        )
+        .add_system_set_to_stage(
+            SimulationStage,
             SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(0.5))
-                .with_system(player_spawn_system)
+                .with_system(player_spawn_system.in_step(GameStep::PlayerSpawn))
+                .with_system(player_keyboard_event_system.in_step(GameStep::Input))
+                .with_system(player_fire_system.in_step(GameStep::PlayerFire))
+                .with_system(player_bomb_system.in_step(GameStep::Bomb))
+                .with_system(player_invulnerability_system.in_step(GameStep::Invulnerability))
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Playing)
+                .with_system(despawn_with::<Player>)
         )
-        .add_system(player_keyboard_event_system)
-        .add_system(player_fire_system)
         ;
     }
 }
 
+/// What the player asks for, read from the `ActionState` every frame and used by the next tick.
+#[derive(Default)]
+pub struct PlayerInput {
+    pub movement: Vec2,
+    /// Fire is held down
+    pub fire: bool,
+    /// Fire went down, stays set until a tick sees it so a quick tap between two ticks is not lost
+    pub fire_pressed: bool,
+    /// latched like `fire_pressed`
+    pub bomb: bool,
+}
+
+/// Alpha masks of the player's sprites, for pixel perfect collisions.
+pub struct PlayerMasks {
+    pub ship: Option<AlphaMask>,
+    pub laser: Option<AlphaMask>,
+}
+
 fn player_spawn_system(
     mut commands : Commands,
     mut player_state: ResMut<PlayerState>,
-    time: Res<Time>,
+    mut state: ResMut<State<AppState>>,
+    sim_time: Res<SimTime>,
     game_textures: Res<GameTextures>,
+    masks: Res<PlayerMasks>,
     win_size :Res<WinSize>)
 {
-    let now = time.seconds_since_startup();
+    let now = sim_time.seconds();
 	let last_shot = player_state.last_shot;
 
 	if !player_state.on && (last_shot == -1. || now > last_shot + PLAYER_RESPAWN_DELAY as f64) {
+		if player_state.lives == 0 {
+			let _ = state.set(AppState::GameOver);
+			return;
+		}
 		// add player
 		let bottom = -win_size.h / 2.;
-		commands
-			.spawn_bundle(SpriteBundle {
-				texture: game_textures.player.clone(),
-				transform: Transform {
-					translation: Vec3::new(
-						0.,
-						bottom + PLAYER_SIZE.1 / 2. * SPRITE_SCALE + 5.,
-						10.,
-					),
-					scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
-					..Default::default()
-				},
+		let mut player = commands.spawn_bundle(SpriteBundle {
+			texture: game_textures.player.clone(),
+			transform: Transform {
+				translation: Vec3::new(
+					0.,
+					bottom + PLAYER_SIZE.1 / 2. * SPRITE_SCALE + 5.,
+					10.,
+				),
+				scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
 				..Default::default()
-			})
+			},
+			..Default::default()
+		});
+		player
 			.insert(Player)
 			.insert(SpriteSize::from(PLAYER_SIZE))
+			.insert(Hitbox::circle(PLAYER_CORE_RADIUS))
+			.insert(CollisionLayer::Player)
 			.insert(Movable { auto_despawn: false })
-			.insert(Velocity { x: 0., y: 0. });
+			.insert(Velocity { x: 0., y: 0. })
+			.insert(Weapon::new(PLAYER_FIRE_RATE, true));
+		if let Some(mask) = &masks.ship {
+			player.insert(mask.clone());
+			if PLAYER_PIXEL_PERFECT {
+				player.insert(PixelPerfect);
+			}
+		}
+		// respawned after being shot
+		if last_shot != -1. {
+			player.insert(Invulnerable::from_seconds(PLAYER_INVULNERABLE_TIME));
+		}
 
 		player_state.spawned();
 	}
 }
 
+fn player_invulnerability_system(
+    mut commands : Commands,
+    sim_time: Res<SimTime>,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility), With<Player>>
+){
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        invulnerable.0.tick(sim_time.delta());
+        if invulnerable.0.finished() {
+            visibility.is_visible = true;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else {
+            let blink = (invulnerable.0.elapsed_secs() / PLAYER_BLINK_PERIOD) as u32;
+            visibility.is_visible = blink % 2 == 1;
+        }
+    }
+}
+
 fn player_fire_system(
     mut commands : Commands,
-    kb: Res<Input<KeyCode>>,
+    mut input: ResMut<PlayerInput>,
+    sim_time: Res<SimTime>,
+    player_state: Res<PlayerState>,
+    tiers: Res<WeaponTiers>,
     game_textures: Res<GameTextures>,
-    query: Query<&Transform,With<Player>>  
+    masks: Res<PlayerMasks>,
+    mut query: Query<(&Transform, &mut Weapon),With<Player>>  
     ){
-        if let Ok(player_tf) = query.get_single() {
-            if kb.just_pressed(KeyCode::Space){
+        let pressed = std::mem::take(&mut input.fire_pressed);
+        if let Ok((player_tf, mut weapon)) = query.get_single_mut() {
+            let tier = tiers.get(player_state.weapon_level);
+            weapon.set_fire_rate(tier.fire_rate);
+            if weapon.trigger(sim_time.delta(), input.fire, pressed){
                 let (x,y) = (player_tf.translation.x,player_tf.translation.y);
-                let x_offset = PLAYER_SIZE.0 / 2. * SPRITE_SCALE -5.;
 
-                let mut spawn_laser = |x_offset: f32 , y_offset: f32| {
-                    commands.spawn_bundle(SpriteBundle {
+                for shot in tier.shots.iter() {
+                    let mut laser = commands.spawn_bundle(SpriteBundle {
                     texture: game_textures.player_laser.clone(),
                         transform: Transform {
-                            translation: Vec3::new(x + x_offset,y + y_offset,6.),
+                            translation: Vec3::new(x + shot.offset.0,y + shot.offset.1,6.),
+                            rotation: shot.rotation(),
                             scale: Vec3::new(SPRITE_SCALE,SPRITE_SCALE,1.),
-                            ..Default::default()
                         },
                         ..Default::default()
-                    })
-                    .insert(Laser)
+                    });
+                    laser.insert(Laser)
                     .insert(FromPlayer)
+                    .insert(Damage(PLAYER_LASER_DAMAGE))
                     .insert(SpriteSize::from(PLAYER_LASER_SIZE))
-                    .insert(Velocity {x:0.,y:1.2})
+                    .insert(Hitbox::capsule(PLAYER_LASER_SIZE))
+                    .insert(CollisionLayer::PlayerProjectile)
+                    .insert(shot.velocity())
                     .insert(Movable {auto_despawn : true});
-                };
-                spawn_laser(0.,15.);
-                spawn_laser(x_offset,5.);
-                spawn_laser(-x_offset,5.);
+                    if let Some(mask) = &masks.laser {
+                        laser.insert(mask.clone());
+                    }
+                    if let Some(turn_rate) = shot.homing {
+                        laser.insert(Homing(turn_rate));
+                    }
+                }
             }
         }
 }
 
+fn player_input_system(actions: Res<ActionState>, mut input: ResMut<PlayerInput>) {
+	input.movement = actions.movement;
+	if actions.pressed(Action::Focus) {
+		input.movement *= PLAYER_FOCUS_SPEED;
+	}
+	input.fire = actions.pressed(Action::Fire);
+	if actions.just_pressed(Action::Fire) {
+		input.fire_pressed = true;
+	}
+	if actions.just_pressed(Action::Bomb) {
+		input.bomb = true;
+	}
+}
+
+/// Clears the enemy fire, hits every enemy on screen and keeps the player safe for a moment.
+fn player_bomb_system(
+    mut commands: Commands,
+    mut input: ResMut<PlayerInput>,
+    mut player_state: ResMut<PlayerState>,
+    win_size: Res<WinSize>,
+    mut bomb_events: EventWriter<BombUsed>,
+    mut killed_events: EventWriter<EnemyKilled>,
+    player_query: Query<(Entity, &Transform, Option<&Invulnerable>), With<Player>>,
+    laser_query: Query<Entity, (With<Laser>, With<FromEnemy>)>,
+    mut enemy_query: Query<(Entity, &Transform, &Enemy, &mut Health)>,
+) {
+    if !std::mem::take(&mut input.bomb) || player_state.bombs == 0 {
+        return;
+    }
+    let (player, player_tf, invulnerable) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    player_state.bombs -= 1;
+    bomb_events.send(BombUsed { position: player_tf.translation });
+
+    for laser in laser_query.iter() {
+        commands.entity(laser).despawn();
+    }
+    for (entity, enemy_tf, enemy, mut health) in enemy_query.iter_mut() {
+        let position = enemy_tf.translation;
+        // still flying in from outside
+        if position.x.abs() > win_size.w / 2. || position.y.abs() > win_size.h / 2. {
+            continue;
+        }
+        if !health.take(BOMB_DAMAGE) {
+            commands.entity(entity).insert(HitFlash::default());
+            continue;
+        }
+        commands.entity(entity).despawn();
+        killed_events.send(EnemyKilled {
+            archetype: enemy.archetype,
+            position,
+        });
+        commands.spawn().insert(ExplosionToSpawn(position));
+    }
+
+    // never cut a longer invulnerability short, the one after respawning for one
+    let remaining = invulnerable
+        .map(|invulnerable| invulnerable.0.duration().saturating_sub(invulnerable.0.elapsed()))
+        .unwrap_or_default();
+    if remaining < Duration::from_secs_f32(BOMB_INVULNERABLE_TIME) {
+        commands.entity(player).insert(Invulnerable::from_seconds(BOMB_INVULNERABLE_TIME));
+    }
+    commands
+        .spawn()
+        .insert(ExplosionToSpawn(player_tf.translation))
+        .insert(ExplosionScale(BOMB_EXPLOSION_SCALE));
+}
+
 fn player_keyboard_event_system(
-	kb: Res<Input<KeyCode>>,
+	input: Res<PlayerInput>,
 	mut query: Query<&mut Velocity, With<Player>>,
 ) { 
 	if let Ok(mut velocity) = query.get_single_mut() {
-		velocity.x = if kb.pressed(KeyCode::Left) {-1.} 
-        else if kb.pressed(KeyCode::Right)        {1.}
-        else {0.};
-
-        velocity.y = if kb.pressed(KeyCode::Up)     {1.}
-        else if kb.pressed(KeyCode::Down)           {-1.}
-        else {0.};
-
+		velocity.x = input.movement.x;
+		velocity.y = input.movement.y;
 	}
 }
 
@@ -0,0 +1,496 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::ecs::schedule::ParallelSystemDescriptor;
+use bevy::prelude::*;
+use bevy::time::{create_time_channels, TimeReceiver, TimeSender};
+
+use crate::controls::{ActionState, ActionSystem};
+use crate::data::DataError;
+use crate::{WinSize, WindowResizeSystem};
+
+const MAGIC: &[u8; 4] = b"OYRP";
+const VERSION: u8 = 3;
+
+/// Records the game to a replay file or plays one back, depending on which of
+/// `ReplayRecorder` / `ReplayPlayback` was inserted before adding it. Does nothing otherwise.
+///
+/// Add it after the window or headless plugin: playback takes over their clock.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        // `WindowResizeSystem` only exists with a window, headless runs have none to wait for
+        let windowed = app.world.contains_resource::<Windows>();
+        let after_resize = |system: ParallelSystemDescriptor| {
+            if windowed {
+                system.after(WindowResizeSystem)
+            } else {
+                system
+            }
+        };
+        if app.world.contains_resource::<ReplayRecorder>() {
+            app.add_system_to_stage(
+                CoreStage::PreUpdate,
+                after_resize(replay_record_system.after(ActionSystem)),
+            )
+            .add_system_to_stage(CoreStage::Last, replay_flush_system);
+        }
+        let playing = app
+            .world
+            .get_resource::<ReplayPlayback>()
+            .is_some_and(|playback| !playback.finished);
+        if playing {
+            // time_system gets its instants from the replay from now on, the old channel
+            // is kept to give it back once the replay is over
+            let (sender, receiver) = create_time_channels();
+            let detached = app
+                .world
+                .remove_resource::<TimeReceiver>()
+                .expect("ReplayPlugin has to be added after the time plugin");
+            let startup = app.world.resource::<Time>().startup();
+            let mut playback = app.world.resource_mut::<ReplayPlayback>();
+            playback.clock = Some((sender, detached));
+            playback.send_instant(startup);
+
+            app.insert_resource(receiver)
+                .add_system_to_stage(
+                    CoreStage::PreUpdate,
+                    after_resize(replay_playback_system.after(ActionSystem)),
+                )
+                .add_system_to_stage(CoreStage::Last, replay_clock_system);
+        }
+    }
+}
+
+/// One tick of input: the `ActionState`, whatever keys or gamepad it came from,
+/// plus the time since the previous tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub actions: ActionState,
+    pub delta: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Record {
+    /// `count` ticks in a row with the same frame
+    Frames(u32, ReplayFrame),
+    Resize(f32, f32),
+}
+
+/// Everything that makes a run: the seed and, per tick, the input, the frame time and
+/// window size changes. Runs of identical ticks are stored once.
+///
+/// File layout, little endian: `OYRP`, version byte, seed u64, skip menu byte, then records:
+/// a varint count followed by pressed and just pressed u16 (a bit per action),
+/// movement x and y f32, varint nanoseconds;
+/// count 0 is a window resize, followed by width and height f32.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    /// recorded headless, the game started straight in `Playing`
+    pub skip_menu: bool,
+    records: Vec<Record>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, DataError> {
+        let bytes = fs::read(path).map_err(|err| DataError::Io(path.to_path_buf(), err))?;
+        Self::decode(&bytes).map_err(|err| DataError::Format(path.to_path_buf(), err))
+    }
+
+    /// Number of ticks.
+    pub fn len(&self) -> u32 {
+        self.records
+            .iter()
+            .map(|record| match record {
+                Record::Frames(count, _) => *count,
+                Record::Resize(..) => 0,
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("replay version {} is not supported", version));
+        }
+        let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let skip_menu = reader.take(1)?[0] != 0;
+
+        let mut records = Vec::new();
+        while !reader.0.is_empty() {
+            let count = reader.varint()? as u32;
+            if count == 0 {
+                let w = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+                let h = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+                records.push(Record::Resize(w, h));
+                continue;
+            }
+            let pressed = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+            let just_pressed = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+            let x = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+            let y = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+            let delta = Duration::from_nanos(reader.varint()?);
+            records.push(Record::Frames(
+                count,
+                ReplayFrame {
+                    actions: ActionState::from_bits(pressed, just_pressed, Vec2::new(x, y)),
+                    delta,
+                },
+            ));
+        }
+        Ok(Self {
+            seed,
+            skip_menu,
+            records,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < n {
+            return Err("unexpected end of file".to_string());
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("broken varint".to_string())
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Writes the replay while the game runs, a run of ticks as soon as it ends.
+pub struct ReplayRecorder {
+    path: PathBuf,
+    out: BufWriter<File>,
+    run: Option<(u32, ReplayFrame)>,
+    win_size: Option<(f32, f32)>,
+    since_startup: Duration,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &Path, seed: u64, skip_menu: bool) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&seed.to_le_bytes())?;
+        out.write_all(&[skip_menu as u8])?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            out,
+            run: None,
+            win_size: None,
+            since_startup: Duration::ZERO,
+        })
+    }
+
+    fn push(&mut self, frame: ReplayFrame) -> io::Result<()> {
+        match &mut self.run {
+            Some((count, run)) if *run == frame && *count < u32::MAX => *count += 1,
+            _ => {
+                self.end_run()?;
+                self.run = Some((1, frame));
+            }
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, w: f32, h: f32) -> io::Result<()> {
+        self.end_run()?;
+        write_varint(&mut self.out, 0)?;
+        self.out.write_all(&w.to_le_bytes())?;
+        self.out.write_all(&h.to_le_bytes())
+    }
+
+    fn end_run(&mut self) -> io::Result<()> {
+        if let Some((count, frame)) = self.run.take() {
+            write_varint(&mut self.out, count as u64)?;
+            let (pressed, just_pressed) = frame.actions.bits();
+            self.out.write_all(&pressed.to_le_bytes())?;
+            self.out.write_all(&just_pressed.to_le_bytes())?;
+            self.out.write_all(&frame.actions.movement.x.to_le_bytes())?;
+            self.out.write_all(&frame.actions.movement.y.to_le_bytes())?;
+            write_varint(&mut self.out, frame.delta.as_nanos() as u64)?;
+        }
+        Ok(())
+    }
+
+    /// Writes out everything recorded so far.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.end_run()?;
+        self.out.flush()
+    }
+}
+
+impl Drop for ReplayRecorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            warn!("could not write replay {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Feeds a `Replay` back into the game: actions into `ActionState`, frame times into `Time`.
+pub struct ReplayPlayback {
+    replay: Replay,
+    record: usize,
+    tick: u32, // within the current `Record::Frames`
+    frames: u32,
+    elapsed: Duration,
+    finished: bool,
+    clock: Option<(TimeSender, TimeReceiver)>,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        let mut playback = Self {
+            replay,
+            record: 0,
+            tick: 0,
+            frames: 0,
+            elapsed: Duration::ZERO,
+            finished: false,
+            clock: None,
+        };
+        playback.finished = playback.frame().is_none();
+        playback
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Ticks played so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Skips over resizes, those are picked up by `resizes_before`.
+    fn frame(&self) -> Option<ReplayFrame> {
+        self.replay.records[self.record..]
+            .iter()
+            .find_map(|record| match record {
+                Record::Frames(_, frame) => Some(*frame),
+                Record::Resize(..) => None,
+            })
+    }
+
+    /// The last window size recorded before the current tick, if it changed.
+    fn resize_before(&self) -> Option<(f32, f32)> {
+        self.replay.records[self.record..]
+            .iter()
+            .take_while(|record| matches!(record, Record::Resize(..)))
+            .last()
+            .map(|record| match record {
+                Record::Resize(w, h) => (*w, *h),
+                Record::Frames(..) => unreachable!(),
+            })
+    }
+
+    fn advance(&mut self) {
+        self.frames += 1;
+        while let Some(record) = self.replay.records.get(self.record) {
+            match record {
+                Record::Resize(..) => self.record += 1,
+                Record::Frames(count, _) => {
+                    self.tick += 1;
+                    if self.tick >= *count {
+                        self.tick = 0;
+                        self.record += 1;
+                    }
+                    break;
+                }
+            }
+        }
+        self.finished = self.frame().is_none();
+    }
+
+    fn send_instant(&mut self, startup: std::time::Instant) {
+        if let (Some(frame), Some((sender, _))) = (self.frame(), &self.clock) {
+            self.elapsed += frame.delta;
+            let _ = sender.0.try_send(startup + self.elapsed);
+        }
+    }
+}
+
+fn replay_record_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    actions: Res<ActionState>,
+    time: Res<Time>,
+    win_size: Res<WinSize>,
+) {
+    // time since startup rather than `delta`, the first tick does not start at 0
+    let since_startup = time.time_since_startup();
+    let frame = ReplayFrame {
+        actions: *actions,
+        delta: since_startup - recorder.since_startup,
+    };
+    recorder.since_startup = since_startup;
+
+    let size = (win_size.w, win_size.h);
+    let result = if recorder.win_size != Some(size) {
+        recorder.win_size = Some(size);
+        recorder.resize(size.0, size.1)
+    } else {
+        Ok(())
+    };
+    if let Err(err) = result.and_then(|_| recorder.push(frame)) {
+        warn!(
+            "could not write replay {}: {}",
+            recorder.path.display(),
+            err
+        );
+    }
+}
+
+fn replay_flush_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut exit_events: EventReader<AppExit>,
+) {
+    if exit_events.iter().count() > 0 {
+        if let Err(err) = recorder.flush() {
+            warn!(
+                "could not write replay {}: {}",
+                recorder.path.display(),
+                err
+            );
+        }
+    }
+}
+
+fn replay_playback_system(
+    playback: Res<ReplayPlayback>,
+    mut actions: ResMut<ActionState>,
+    mut win_size: ResMut<WinSize>,
+) {
+    let frame = match playback.frame() {
+        Some(frame) if !playback.finished => frame,
+        _ => return,
+    };
+    if let Some((w, h)) = playback.resize_before() {
+        win_size.w = w;
+        win_size.h = h;
+    }
+    *actions = frame.actions;
+}
+
+/// Queues the next tick's instant, and hands the clock back when the replay is over.
+fn replay_clock_system(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    time: Res<Time>,
+) {
+    if playback.finished {
+        return;
+    }
+    playback.advance();
+    if playback.finished {
+        info!("replay finished after {} frames", playback.frames);
+        if let Some((_, detached)) = playback.clock.take() {
+            commands.insert_resource(detached);
+        }
+        return;
+    }
+    if let Some((_, detached)) = &playback.clock {
+        // whoever fed the old channel (renderer, headless clock) is still sending
+        while detached.0.try_recv().is_ok() {}
+    }
+    playback.send_instant(time.startup());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pressed: u16, just_pressed: u16, x: f32, nanos: u64) -> ReplayFrame {
+        ReplayFrame {
+            actions: ActionState::from_bits(pressed, just_pressed, Vec2::new(x, 0.)),
+            delta: Duration::from_nanos(nanos),
+        }
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 300, 16_666_667, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value).unwrap();
+            let mut reader = Reader(&bytes);
+            assert_eq!(reader.varint().unwrap(), value);
+            assert!(reader.0.is_empty());
+        }
+    }
+
+    #[test]
+    fn recorded_runs_decode_back() {
+        let path =
+            std::env::temp_dir().join(format!("bevy_oyun_replay_{}.rpl", std::process::id()));
+        let held = frame(0b1_0000, 0, -1., 16_666_667);
+        let fired = frame(0b1_0000, 0b1_0000, 0.5, 16_666_666);
+        {
+            let mut recorder = ReplayRecorder::create(&path, 1234, true).unwrap();
+            recorder.resize(598., 676.).unwrap();
+            for _ in 0..300 {
+                recorder.push(held).unwrap();
+            }
+            recorder.push(fired).unwrap();
+            recorder.resize(800., 600.).unwrap();
+            recorder.push(fired).unwrap();
+        }
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let replay = Replay::decode(&bytes).unwrap();
+        assert_eq!(replay.seed, 1234);
+        assert!(replay.skip_menu);
+        assert_eq!(
+            replay.records,
+            vec![
+                Record::Resize(598., 676.),
+                Record::Frames(300, held),
+                Record::Frames(1, fired),
+                Record::Resize(800., 600.),
+                Record::Frames(1, fired),
+            ]
+        );
+        assert_eq!(replay.len(), 302);
+
+        assert!(Replay::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::decode(b"NOPE").is_err());
+    }
+}
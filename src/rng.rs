@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::state::AppState;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        // main puts a seeded one here for `--seed`
+        if !app.world.contains_resource::<GameRng>() {
+            app.insert_resource(GameRng::from_entropy());
+        }
+        info!("game seed: {}", app.world.resource::<GameRng>().seed());
+        app.add_system_set(SystemSet::on_enter(AppState::Playing).with_system(rng_reset_system));
+    }
+}
+
+/// The one source of gameplay randomness. Every game starts over from the same seed,
+/// so the same seed and the same inputs play out the same game.
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::new(rand::thread_rng().next_u64())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+fn rng_reset_system(mut rng: ResMut<GameRng>) {
+    let seed = rng.seed;
+    *rng = GameRng::new(seed);
+}
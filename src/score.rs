@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::enemy::archetype::EnemyArchetypes;
+use crate::events::{BossPartDestroyed, EnemyKilled, PlayerHit};
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{AppState, GameStep, InGameStep};
+use crate::{COMBO_MAX, COMBO_WINDOW, HIGH_SCORE_COUNT, HIGH_SCORE_FILE};
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        // HeadlessPlugin puts an in-memory table here so simulations never touch the disk
+        if !app.world.contains_resource::<HighScores>() {
+            app.insert_resource(HighScores::load());
+        }
+        app.insert_resource(Score::default())
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(|mut commands: Commands| {
+                    commands.insert_resource(Score::default())
+                }),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_system(score_system.in_step(GameStep::Score))
+                    .with_system(combo_timeout_system.in_step(GameStep::Score)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Playing).with_system(high_score_system));
+    }
+}
+
+pub struct Score {
+    pub points: u32,
+    pub combo: u32,
+    pub last_kill: f64, //-1 if nothing killed yet
+}
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            points: 0,
+            combo: 1,
+            last_kill: -1.,
+        }
+    }
+}
+impl Score {
+    pub fn kill(&mut self, points: u32, time: f64) {
+        if self.last_kill >= 0. && time - self.last_kill <= COMBO_WINDOW {
+            self.combo = (self.combo + 1).min(COMBO_MAX);
+        } else {
+            self.combo = 1;
+        }
+        self.last_kill = time;
+        self.points += points * self.combo;
+    }
+    pub fn reset_combo(&mut self) {
+        self.combo = 1;
+        self.last_kill = -1.;
+    }
+}
+
+/// Best scores, highest first. Kept in the user data directory, one score per line.
+pub struct HighScores {
+    pub path: Option<PathBuf>,
+    pub scores: Vec<u32>,
+}
+impl HighScores {
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            scores: Vec::new(),
+        }
+    }
+
+    pub fn load() -> Self {
+        let path = dirs::data_dir().map(|dir| dir.join("bevy_oyun").join(HIGH_SCORE_FILE));
+        let mut scores: Vec<u32> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| line.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+        scores.truncate(HIGH_SCORE_COUNT);
+        Self { path, scores }
+    }
+
+    pub fn best(&self) -> u32 {
+        self.scores.first().copied().unwrap_or(0)
+    }
+
+    /// Puts `points` into the table if it is good enough, returns its rank.
+    pub fn submit(&mut self, points: u32) -> Option<usize> {
+        if points == 0 {
+            return None;
+        }
+        let rank = self
+            .scores
+            .iter()
+            .position(|&s| points > s)
+            .unwrap_or(self.scores.len());
+        if rank >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        self.scores.insert(rank, points);
+        self.scores.truncate(HIGH_SCORE_COUNT);
+        Some(rank)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let text: Vec<String> = self.scores.iter().map(|s| s.to_string()).collect();
+            fs::write(path, text.join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+fn score_system(
+    mut score: ResMut<Score>,
+    sim_time: Res<SimTime>,
+    archetypes: Res<EnemyArchetypes>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut boss_events: EventReader<BossPartDestroyed>,
+    mut hit_events: EventReader<PlayerHit>,
+) {
+    let now = sim_time.seconds();
+    for killed in killed_events.iter() {
+        score.kill(archetypes.0[killed.archetype].score, now);
+    }
+    for destroyed in boss_events.iter() {
+        score.kill(destroyed.score, now);
+    }
+    if hit_events.iter().count() > 0 {
+        score.reset_combo();
+    }
+}
+
+fn combo_timeout_system(mut score: ResMut<Score>, sim_time: Res<SimTime>) {
+    if score.combo > 1 && sim_time.seconds() - score.last_kill > COMBO_WINDOW {
+        score.combo = 1;
+    }
+}
+
+fn high_score_system(score: Res<Score>, mut high_scores: ResMut<HighScores>) {
+    if high_scores.submit(score.points).is_some() {
+        if let Err(err) = high_scores.save() {
+            warn!("could not save high scores: {}", err);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::state::{AppState, GameStep};
+use crate::TIME_STEP;
+
+/// At most this many ticks per frame. A frame taking longer than that slows the game down
+/// instead of making the next frame even slower.
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// Everything that changes the game runs here, in ticks of `TIME_STEP`, however fast
+/// the frames come. Add gameplay systems with `add_system_set_to_stage(SimulationStage, ..)`.
+///
+/// The stage only ticks while `Playing`, so its sets need no state criteria. They must not
+/// use `SystemSet::on_update` either: state criteria expect the state driver, which lives in
+/// `CoreStage::Update`, and loop forever anywhere else.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct SimulationStage;
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimClock::default())
+            .insert_resource(SimTime::default())
+            .add_stage_after(
+                CoreStage::Update,
+                SimulationStage,
+                SystemStage::parallel().with_run_criteria(simulation_tick_criteria),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, restore_transform_system)
+            .add_system_to_stage(
+                SimulationStage,
+                tick_start_system.before(GameStep::FIRST),
+            )
+            .add_system_to_stage(SimulationStage, tick_end_system.after(GameStep::LAST))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate_transform_system.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// The length of one tick. The headless clock steps by exactly this much.
+pub fn tick_duration() -> Duration {
+    Duration::from_secs_f32(TIME_STEP)
+}
+
+/// Real time not simulated yet.
+#[derive(Default)]
+pub struct SimClock {
+    accumulator: Duration,
+    ticks_this_frame: u32,
+    looping: bool,
+}
+
+impl SimClock {
+    /// How far the frame is between the last tick and the next one, 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / TIME_STEP
+    }
+}
+
+/// Game time, counted in ticks. Simulation systems use this instead of `Time`.
+#[derive(Default)]
+pub struct SimTime {
+    pub tick: u64,
+}
+
+impl SimTime {
+    pub fn delta(&self) -> Duration {
+        tick_duration()
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        TIME_STEP
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.tick as f64 * TIME_STEP as f64
+    }
+}
+
+/// Drawn between where the entity was on the last two ticks, so movement looks smooth
+/// whatever the frame rate. Added to every sprite by the simulation.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl Interpolated {
+    /// The entity jumped (wrapped around, respawned...) during this tick, do not draw
+    /// it sliding across the screen.
+    pub fn teleport(&mut self, to: Vec3) {
+        self.previous = to;
+    }
+}
+
+fn simulation_tick_criteria(
+    mut clock: ResMut<SimClock>,
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+) -> ShouldRun {
+    // game time stands still in the menus and while paused
+    if state.current() != &AppState::Playing {
+        clock.accumulator = Duration::ZERO;
+        clock.looping = false;
+        return ShouldRun::No;
+    }
+    if !clock.looping {
+        clock.accumulator += time.delta();
+        clock.ticks_this_frame = 0;
+    }
+    if clock.accumulator >= tick_duration() && clock.ticks_this_frame < MAX_TICKS_PER_FRAME {
+        clock.accumulator -= tick_duration();
+        clock.ticks_this_frame += 1;
+        clock.looping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        // whatever is left over after too many ticks is dropped
+        if clock.ticks_this_frame == MAX_TICKS_PER_FRAME {
+            clock.accumulator = clock.accumulator.min(tick_duration());
+        }
+        clock.looping = false;
+        ShouldRun::No
+    }
+}
+
+fn tick_start_system(
+    mut sim_time: ResMut<SimTime>,
+    mut query: Query<(&Transform, &mut Interpolated)>,
+) {
+    sim_time.tick += 1;
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = transform.translation;
+    }
+}
+
+fn tick_end_system(
+    mut commands: Commands,
+    mut query: Query<(&Transform, &mut Interpolated)>,
+    new_sprites: Query<(Entity, &Transform), (With<Sprite>, Without<Interpolated>)>,
+) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.current = transform.translation;
+    }
+    for (entity, transform) in new_sprites.iter() {
+        let interpolated = Interpolated {
+            previous: transform.translation,
+            current: transform.translation,
+        };
+        // a sprite can be despawned this very tick, by commands applied before these
+        commands.add(move |world: &mut World| {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                entity.insert(interpolated);
+            }
+        });
+    }
+}
+
+/// Puts the simulated position back before anything else looks at it.
+fn restore_transform_system(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.current;
+    }
+}
+
+fn interpolate_transform_system(
+    clock: Res<SimClock>,
+    mut query: Query<(&mut Transform, &Interpolated)>,
+) {
+    let alpha = clock.alpha();
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
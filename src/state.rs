@@ -0,0 +1,223 @@
+use bevy::ecs::schedule::ParallelSystemDescriptor;
+use bevy::prelude::*;
+
+use crate::controls::{Action, ActionState, Controls};
+use crate::score::{HighScores, Score};
+use crate::GameTextures;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Text shown on top of the game for menu, pause and game over screens.
+#[derive(Component)]
+pub struct Overlay;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(AppState::MainMenu)
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(main_menu_enter))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(main_menu_input))
+            .add_system_set(
+                SystemSet::on_exit(AppState::MainMenu).with_system(despawn_with::<Overlay>),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(pause_input),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(paused_enter))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(paused_input))
+            .add_system_set(
+                SystemSet::on_exit(AppState::Paused).with_system(despawn_with::<Overlay>),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(game_over_enter))
+            .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(game_over_input))
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver).with_system(despawn_with::<Overlay>),
+            );
+    }
+}
+
+/// Despawns every entity carrying `T`, used to clean up when leaving a state.
+pub fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Gameplay systems in the `SimulationStage` run in this order, one step after the other. Left to bevy, the order
+/// of unrelated systems changes from run to run, and with it the order entities get
+/// spawned in, so the same inputs would not always play out the same game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemLabel)]
+pub enum GameStep {
+    Input,
+    PlayerSpawn,
+    PlayerFire,
+    Bomb,
+    EnemyFire,
+    Waves,
+    Boss,
+    Steering,
+    Movement,
+    Collision,
+    PlayerHits,
+    BossHits,
+    EnemyHits,
+    Pickups,
+    Invulnerability,
+    HitFlash,
+    Score,
+}
+
+impl GameStep {
+    pub const FIRST: GameStep = GameStep::Input;
+    pub const LAST: GameStep = GameStep::Score;
+
+    const ORDER: [GameStep; 17] = [
+        GameStep::Input,
+        GameStep::PlayerSpawn,
+        GameStep::PlayerFire,
+        GameStep::Bomb,
+        GameStep::EnemyFire,
+        GameStep::Waves,
+        GameStep::Boss,
+        GameStep::Steering,
+        GameStep::Movement,
+        GameStep::Collision,
+        GameStep::PlayerHits,
+        GameStep::BossHits,
+        GameStep::EnemyHits,
+        GameStep::Pickups,
+        GameStep::Invulnerability,
+        GameStep::HitFlash,
+        GameStep::Score,
+    ];
+
+    fn previous(self) -> Option<GameStep> {
+        let index = Self::ORDER.iter().position(|&step| step == self)?;
+        index.checked_sub(1).map(|i| Self::ORDER[i])
+    }
+}
+
+/// `system.in_step(GameStep::Movement)` labels the system and puts it after the step before.
+pub trait InGameStep<Params> {
+    fn in_step(self, step: GameStep) -> ParallelSystemDescriptor;
+}
+
+impl<S, Params> InGameStep<Params> for S
+where
+    S: ParallelSystemDescriptorCoercion<Params>,
+{
+    fn in_step(self, step: GameStep) -> ParallelSystemDescriptor {
+        let system = self.label(step);
+        match step.previous() {
+            Some(previous) => system.after(previous),
+            None => system,
+        }
+    }
+}
+
+/// Startup system going straight into `Playing`, for headless runs and replays.
+pub fn skip_main_menu_system(mut state: ResMut<State<AppState>>) {
+    // the startup transition is still pending here, so a plain `set` would be refused
+    let _ = state.overwrite_set(AppState::Playing);
+}
+
+fn spawn_overlay(commands: &mut Commands, game_textures: &GameTextures, text: &str) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..Default::default()
+        })
+        .insert(Overlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font: game_textures.font.clone(),
+                        font_size: 36.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::CENTER),
+            );
+        });
+}
+
+fn main_menu_enter(mut commands: Commands, game_textures: Res<GameTextures>, controls: Res<Controls>) {
+    let text = format!("ilk oyun\n\n{}: start", controls.key_name(Action::Fire));
+    spawn_overlay(&mut commands, &game_textures, &text);
+}
+
+fn main_menu_input(mut actions: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Fire) {
+        let _ = state.set(AppState::Playing);
+        actions.consume(Action::Fire);
+    }
+}
+
+fn pause_input(mut actions: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Pause) {
+        let _ = state.push(AppState::Paused);
+        actions.consume(Action::Pause);
+    }
+}
+
+fn paused_enter(mut commands: Commands, game_textures: Res<GameTextures>, controls: Res<Controls>) {
+    let text = format!(
+        "PAUSED\n\n{}: resume\n{}: main menu",
+        controls.key_name(Action::Pause),
+        controls.key_name(Action::Back)
+    );
+    spawn_overlay(&mut commands, &game_textures, &text);
+}
+
+fn paused_input(mut actions: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Pause) {
+        let _ = state.pop();
+        actions.consume(Action::Pause);
+    } else if actions.just_pressed(Action::Back) {
+        let _ = state.replace(AppState::MainMenu);
+        actions.consume(Action::Back);
+    }
+}
+
+fn game_over_enter(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    controls: Res<Controls>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+) {
+    let text = format!(
+        "GAME OVER\n\nscore: {}\nbest: {}\n\n{}: play again\n{}: main menu",
+        score.points,
+        high_scores.best(),
+        controls.key_name(Action::Fire),
+        controls.key_name(Action::Back)
+    );
+    spawn_overlay(&mut commands, &game_textures, &text);
+}
+
+fn game_over_input(mut actions: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Fire) {
+        let _ = state.set(AppState::Playing);
+        actions.consume(Action::Fire);
+    } else if actions.just_pressed(Action::Back) {
+        let _ = state.set(AppState::MainMenu);
+        actions.consume(Action::Back);
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::boss::kind::BossKinds;
+use crate::boss::{spawn_boss, Boss};
+use crate::components::Enemy;
+use crate::data::{asset_path, load_ron};
+use crate::enemy::archetype::EnemyArchetypes;
+use crate::enemy::formation::{FormationKind, FormationMaker};
+use crate::enemy::orbit::OrbitFormations;
+use crate::enemy::path::PathFormations;
+use crate::enemy::spawn_enemy;
+use crate::rng::GameRng;
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{AppState, GameStep, InGameStep};
+use crate::{WinSize, LEVEL_SCRIPT};
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        let script: LevelScript = load_ron(&asset_path(LEVEL_SCRIPT))
+            .unwrap_or_else(|err| panic!("could not load level script: {}", err));
+        let script = script
+            .resolve(
+                app.world.resource::<EnemyArchetypes>(),
+                app.world.resource::<BossKinds>(),
+                app.world.resource::<PathFormations>(),
+            )
+            .unwrap_or_else(|err| panic!("{}: {}", LEVEL_SCRIPT, err));
+
+        app.insert_resource(WaveDirector::new(script))
+            .insert_resource(WaveNumber(1))
+            .add_event::<WaveStarted>()
+            .add_event::<WaveEnded>()
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(wave_reset_system))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SystemSet::new()
+                    .with_system(wave_director_system.in_step(GameStep::Waves)),
+            );
+    }
+}
+
+/// Current wave, counting from 1. Keeps growing when the script starts over.
+pub struct WaveNumber(pub u32);
+
+pub struct WaveStarted {
+    pub number: u32,
+}
+
+pub struct WaveEnded {
+    pub number: u32,
+    /// false if the wave timed out instead
+    pub cleared: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct LevelScript {
+    pub waves: Vec<Wave>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Wave {
+    pub spawns: Vec<SpawnGroup>,
+    pub clear: ClearCondition,
+    #[serde(default)]
+    pub timeout: Option<f32>,
+    /// a boss from `assets/bosses/`, flying in as the wave starts
+    #[serde(default)]
+    pub boss: Option<String>,
+    #[serde(skip)]
+    pub boss_index: Option<usize>,
+}
+
+/// `count` enemies of one archetype, the first after `delay` seconds and then one every `interval`.
+#[derive(Clone, Deserialize)]
+pub struct SpawnGroup {
+    pub archetype: String,
+    pub count: u32,
+    #[serde(default)]
+    pub delay: f32,
+    pub interval: f32,
+    #[serde(default)]
+    pub formation: Option<FormationKind>,
+    #[serde(skip)]
+    pub archetype_index: usize,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum ClearCondition {
+    /// everything spawned and nothing left alive, the boss included
+    AllKilled,
+    /// the wave ends after this many seconds
+    Survive(f32),
+}
+
+impl LevelScript {
+    /// Looks up the archetype, formation and boss names, so a typo fails at startup and not mid-game.
+    pub fn resolve(
+        mut self,
+        archetypes: &EnemyArchetypes,
+        bosses: &BossKinds,
+        paths: &PathFormations,
+    ) -> Result<Self, String> {
+        if self.waves.is_empty() {
+            return Err("level script has no waves".to_string());
+        }
+        for group in self.waves.iter_mut().flat_map(|wave| wave.spawns.iter_mut()) {
+            group.archetype_index = archetypes
+                .index_of(&group.archetype)
+                .ok_or_else(|| format!("unknown enemy archetype \"{}\"", group.archetype))?;
+            if let Some(formation) = &group.formation {
+                formation.check(paths)?;
+            }
+        }
+        for wave in self.waves.iter_mut() {
+            if let Some(boss) = &wave.boss {
+                wave.boss_index = Some(
+                    bosses
+                        .index_of(boss)
+                        .ok_or_else(|| format!("unknown boss \"{}\"", boss))?,
+                );
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Walks through the level script: spawns the current wave and moves on when it is over.
+pub struct WaveDirector {
+    pub script: LevelScript,
+    pub index: usize,
+    pub elapsed: f32,
+    pub spawned: Vec<u32>, //per spawn group of the current wave
+    pub boss_spawned: bool,
+}
+
+impl WaveDirector {
+    pub fn new(script: LevelScript) -> Self {
+        let mut director = Self {
+            script,
+            index: 0,
+            elapsed: 0.,
+            spawned: Vec::new(),
+            boss_spawned: false,
+        };
+        director.start(0);
+        director
+    }
+
+    pub fn wave(&self) -> &Wave {
+        &self.script.waves[self.index]
+    }
+
+    fn start(&mut self, index: usize) {
+        self.index = index % self.script.waves.len();
+        self.elapsed = 0.;
+        self.spawned = vec![0; self.script.waves[self.index].spawns.len()];
+        self.boss_spawned = false;
+    }
+
+    fn all_spawned(&self) -> bool {
+        self.wave()
+            .spawns
+            .iter()
+            .zip(self.spawned.iter())
+            .all(|(group, &spawned)| spawned >= group.count)
+            && (self.wave().boss.is_none() || self.boss_spawned)
+    }
+}
+
+fn wave_reset_system(
+    mut commands: Commands,
+    director: Res<WaveDirector>,
+    mut started_events: EventWriter<WaveStarted>,
+) {
+    commands.insert_resource(WaveDirector::new(director.script.clone()));
+    commands.insert_resource(WaveNumber(1));
+    started_events.send(WaveStarted { number: 1 });
+}
+
+fn wave_director_system(
+    mut commands: Commands,
+    mut director: ResMut<WaveDirector>,
+    mut wave_number: ResMut<WaveNumber>,
+    mut formation_maker: ResMut<FormationMaker>,
+    mut rng: ResMut<GameRng>,
+    archetypes: Res<EnemyArchetypes>,
+    bosses: Res<BossKinds>,
+    orbits: Res<OrbitFormations>,
+    paths: Res<PathFormations>,
+    sim_time: Res<SimTime>,
+    win_size: Res<WinSize>,
+    enemy_query: Query<(), Or<(With<Enemy>, With<Boss>)>>,
+    mut started_events: EventWriter<WaveStarted>,
+    mut ended_events: EventWriter<WaveEnded>,
+) {
+    director.elapsed += sim_time.delta_seconds();
+    let elapsed = director.elapsed;
+
+    // spawn whatever is due, borrowing the wave next to the counts
+    let director = &mut *director;
+    let wave = &director.script.waves[director.index];
+    let mut spawned_now = false;
+    for (group, spawned) in wave.spawns.iter().zip(director.spawned.iter_mut()) {
+        while *spawned < group.count && elapsed >= group.delay + group.interval * *spawned as f32 {
+            let archetype = &archetypes.0[group.archetype_index];
+            let kind = group.formation.as_ref().unwrap_or(&archetype.formation);
+            let movement = formation_maker.make(kind, &win_size, &mut *rng, &orbits, &paths);
+            spawn_enemy(&mut commands, group.archetype_index, archetype, movement);
+            *spawned += 1;
+            spawned_now = true;
+        }
+    }
+    let (boss_index, clear, timeout) = (wave.boss_index, wave.clear, wave.timeout);
+    if let (Some(index), false) = (boss_index, director.boss_spawned) {
+        spawn_boss(&mut commands, index, &bosses.0[index], &win_size);
+        director.boss_spawned = true;
+        spawned_now = true;
+    }
+
+    // enemies spawned just now are not in the query yet, so wait for everything to be out
+    let cleared = match clear {
+        ClearCondition::AllKilled => {
+            !spawned_now && director.all_spawned() && enemy_query.is_empty()
+        }
+        ClearCondition::Survive(duration) => elapsed >= duration,
+    };
+    let timed_out = timeout.is_some_and(|timeout| elapsed >= timeout);
+
+    if cleared || timed_out {
+        ended_events.send(WaveEnded {
+            number: wave_number.0,
+            cleared,
+        });
+        let next = director.index + 1;
+        director.start(next);
+        wave_number.0 += 1;
+        started_events.send(WaveStarted {
+            number: wave_number.0,
+        });
+    }
+}
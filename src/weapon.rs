@@ -0,0 +1,141 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::CollisionLayer;
+use crate::components::Velocity;
+use crate::data::{asset_path, load_ron, DataError};
+use crate::simulation::{SimTime, SimulationStage};
+use crate::state::{GameStep, InGameStep};
+use crate::PLAYER_WEAPONS;
+
+/// The player's weapon tiers, and homing lasers.
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        let tiers = WeaponTiers::load(PLAYER_WEAPONS)
+            .unwrap_or_else(|err| panic!("could not load weapon tiers: {}", err));
+        app.insert_resource(tiers).add_system_set_to_stage(
+            SimulationStage,
+            SystemSet::new().with_system(homing_system.in_step(GameStep::Steering)),
+        );
+    }
+}
+
+/// What the player fires at one weapon level, read from `assets/weapons.ron`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeaponTier {
+    pub name: String,
+    /// shots per second while Fire is held
+    pub fire_rate: f32,
+    pub shots: Vec<Shot>,
+}
+
+/// One laser of a volley.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Shot {
+    /// from the ship's center
+    #[serde(default)]
+    pub offset: (f32, f32),
+    /// degrees, clockwise from straight up
+    #[serde(default)]
+    pub angle: f32,
+    /// in the same unit as `Velocity`
+    #[serde(default = "default_shot_speed")]
+    pub speed: f32,
+    /// turns towards the nearest enemy, in degrees per second
+    #[serde(default)]
+    pub homing: Option<f32>,
+}
+
+fn default_shot_speed() -> f32 {
+    1.2
+}
+
+impl Shot {
+    pub fn velocity(&self) -> Velocity {
+        let angle = self.angle.to_radians();
+        Velocity {
+            x: angle.sin() * self.speed,
+            y: angle.cos() * self.speed,
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_z(-self.angle.to_radians())
+    }
+}
+
+#[derive(Deserialize)]
+struct WeaponFile {
+    tiers: Vec<WeaponTier>,
+}
+
+/// Weapon levels count from 1, `PlayerState::weapon_level` 1 is the first tier.
+pub struct WeaponTiers(pub Vec<WeaponTier>);
+
+impl WeaponTiers {
+    pub fn load(file: &str) -> Result<Self, DataError> {
+        let path = asset_path(file);
+        let WeaponFile { tiers } = load_ron(&path)?;
+        if tiers.is_empty() {
+            return Err(DataError::Format(path, "no weapon tiers".to_string()));
+        }
+        if let Some(tier) = tiers.iter().find(|tier| tier.fire_rate <= 0.) {
+            return Err(DataError::Format(
+                path,
+                format!("{}: fire_rate has to be above 0", tier.name),
+            ));
+        }
+        Ok(Self(tiers))
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    /// The tier for a weapon level, clamped to the ones there are.
+    pub fn get(&self, level: u32) -> &WeaponTier {
+        &self.0[(level.max(1) as usize - 1).min(self.0.len() - 1)]
+    }
+}
+
+/// Steers the laser towards the nearest enemy, boss or turret, `0` is the turn rate
+/// in degrees per second.
+#[derive(Component)]
+pub struct Homing(pub f32);
+
+fn homing_system(
+    sim_time: Res<SimTime>,
+    mut lasers: Query<(&Homing, &mut Velocity, &mut Transform)>,
+    targets: Query<(&Transform, &CollisionLayer), Without<Homing>>,
+) {
+    for (homing, mut velocity, mut transform) in lasers.iter_mut() {
+        let position = transform.translation.xy();
+        let target = targets
+            .iter()
+            .filter(|(_, layer)| **layer == CollisionLayer::Enemy)
+            .map(|(target, _)| target.translation.xy())
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            });
+        let target = match target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let current = Vec2::new(velocity.x, velocity.y);
+        let wanted = target - position;
+        let max_turn = homing.0.to_radians() * sim_time.delta_seconds();
+        let turn = current.angle_between(wanted).clamp(-max_turn, max_turn);
+        let heading = Vec2::from_angle(current.y.atan2(current.x) + turn) * current.length();
+        velocity.x = heading.x;
+        velocity.y = heading.y;
+        // the sprite points up
+        transform.rotation = Quat::from_rotation_z(heading.y.atan2(heading.x) - FRAC_PI_2);
+    }
+}
@@ -0,0 +1,47 @@
+//! A recorded headless run played back has to end up exactly the same.
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_oyun::headless::{self, HeadlessPlugin};
+use bevy_oyun::replay::{Replay, ReplayPlayback, ReplayPlugin, ReplayRecorder};
+use bevy_oyun::rng::GameRng;
+use bevy_oyun::ShooterPlugin;
+
+const SEED: u64 = 7;
+const FRAMES: u32 = 1200;
+
+#[test]
+fn replay_matches_the_recorded_run() {
+    let path = std::env::temp_dir().join(format!("bevy_oyun_run_{}.rpl", std::process::id()));
+
+    let recorded = {
+        let mut app = App::new();
+        app.insert_resource(ReplayRecorder::create(&path, SEED, true).unwrap())
+            .insert_resource(GameRng::new(SEED))
+            .add_plugin(HeadlessPlugin::default())
+            .add_plugin(ShooterPlugin)
+            .add_plugin(ReplayPlugin);
+        // hold fire and drift left, so there is input to record
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.press(KeyCode::Space);
+        keys.press(KeyCode::Left);
+        headless::run_frames(&mut app, FRAMES)
+        // the recorder writes the rest of the file as the app is dropped
+    };
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.len(), FRAMES);
+
+    let mut app = App::new();
+    app.insert_resource(GameRng::new(replay.seed))
+        .insert_resource(ReplayPlayback::new(replay))
+        .add_plugin(HeadlessPlugin::default())
+        .add_plugin(ShooterPlugin)
+        .add_plugin(ReplayPlugin);
+    let replayed = headless::run_frames(&mut app, FRAMES);
+
+    assert!(recorded.player_lasers > 0);
+    assert!(recorded.enemies_spawned > 0);
+    assert_eq!(recorded, replayed);
+}